@@ -0,0 +1,58 @@
+//! Image attachments for vision-capable models.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::fs;
+use std::path::Path;
+
+/// Largest image we are willing to inline into a request.
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// An image read from disk, ready to send as a `data:` URL content part.
+pub struct ImageAttachment {
+    pub data_url: String,
+}
+
+impl ImageAttachment {
+    /// Read and base64-encode the image at `path`, checking size and format.
+    pub fn load(path: &Path) -> Result<Self> {
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .len();
+        if size > MAX_IMAGE_BYTES {
+            anyhow::bail!(
+                "{} is {} MB; images are limited to {} MB",
+                path.display(),
+                size / (1024 * 1024),
+                MAX_IMAGE_BYTES / (1024 * 1024)
+            );
+        }
+
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mime = sniff_mime(&bytes).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} is not a supported image (PNG, JPEG, GIF or WebP)",
+                path.display()
+            )
+        })?;
+
+        Ok(Self {
+            data_url: format!("data:{};base64,{}", mime, STANDARD.encode(&bytes)),
+        })
+    }
+}
+
+/// Identify the image format from its magic bytes rather than the extension.
+fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
@@ -1,14 +1,25 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
-use crate::jwt::parse_jwt_claims;
+use crate::jwt::{load_jwks, parse_claims, verify_jwt, Claims};
+use crate::log;
+use crate::paths;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Refresh tokens this long before they actually expire.
+const REFRESH_SKEW: Duration = Duration::from_secs(300);
+
+/// `aud` claim of ChatGPT access tokens.
+const ACCESS_TOKEN_AUDIENCE: &str = "https://api.openai.com/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Tokens {
     pub id_token: String,
     pub access_token: String,
@@ -16,6 +27,27 @@ pub struct Tokens {
     pub account_id: String,
 }
 
+/// Why [`AuthData::verify`] failed: the token itself, or getting the keys to
+/// check it with.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The issuer's JWKS could not be fetched or parsed.
+    KeyFetch(anyhow::Error),
+    /// The signature or a claim is wrong.
+    Invalid(anyhow::Error),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyFetch(e) => write!(f, "could not load signing keys: {:#}", e),
+            Self::Invalid(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthData {
     pub tokens: Tokens,
@@ -39,42 +71,123 @@ impl AuthData {
             fs::create_dir_all(parent)?;
         }
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(&path, &content)?;
+
+        // Write to a temp file and rename over auth.json so a concurrent reader
+        // never sees a half-written file.
+        let tmp = path.with_extension(format!("json.tmp.{}", std::process::id()));
+        fs::write(&tmp, &content)?;
 
         // Set file permissions to 600 (owner read/write only) - Unix only
         #[cfg(unix)]
         {
-            let mut perms = fs::metadata(&path)?.permissions();
+            let mut perms = fs::metadata(&tmp)?.permissions();
             perms.set_mode(0o600);
-            fs::set_permissions(&path, perms)?;
+            fs::set_permissions(&tmp, perms)?;
         }
 
+        fs::rename(&tmp, &path)?;
         Ok(())
     }
 
+    /// Take an exclusive advisory lock on `auth.lock`, held until the returned
+    /// file is dropped. Serializes token refreshes across `jose` processes.
+    fn lock() -> Result<fs::File> {
+        let path = Self::auth_path()?.with_file_name("auth.lock");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        file.lock().context("Failed to lock auth.lock")?;
+        Ok(file)
+    }
+
     fn auth_path() -> Result<PathBuf> {
-        let home =
-            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        Ok(home.join(".jose").join("auth.json"))
-    }
-
-    /// Check if the access token is expired or about to expire
-    pub fn needs_refresh(&self) -> bool {
-        if let Some(claims) = parse_jwt_claims(&self.tokens.access_token) {
-            if let Some(exp) = claims.get("exp").and_then(|v| v.as_i64()) {
-                let now = chrono::Utc::now().timestamp();
-                // Refresh if token expires within 5 minutes
-                return exp <= now + 300;
+        paths::data_file("auth.json")
+    }
+
+    /// Check if the access token is expired or about to expire. With
+    /// `verify_jwt` set, a token whose signature or claims don't check out
+    /// counts as expired so it gets replaced. If the issuer's keys can't be
+    /// fetched, only the `exp` check above applies.
+    pub fn needs_refresh(&self, config: &Config) -> bool {
+        if self.describe().needs_refresh {
+            return true;
+        }
+        if !config.verify_jwt {
+            return false;
+        }
+        match self.verify(config) {
+            Ok(()) => false,
+            Err(VerifyError::KeyFetch(e)) => {
+                log::warn(&format!("Skipping token verification: {:#}", e));
+                false
             }
+            Err(e @ VerifyError::Invalid(_)) => {
+                log::warn(&format!("Stored access token failed verification: {}", e));
+                true
+            }
+        }
+    }
+
+    /// Verify the access token's signature and `exp`/`iss`/`aud` claims.
+    pub fn verify(&self, config: &Config) -> Result<(), VerifyError> {
+        let jwks = load_jwks(config).map_err(VerifyError::KeyFetch)?;
+        let (token, issuer) = (&self.tokens.access_token, config.oauth_issuer());
+        verify_jwt(token, &jwks, &issuer, ACCESS_TOKEN_AUDIENCE).map_err(VerifyError::Invalid)?;
+        Ok(())
+    }
+
+    /// Decode the stored tokens once into a status every command can render.
+    pub fn describe(&self) -> AuthStatus {
+        let access = parse_claims(&self.tokens.access_token);
+        let expires_at = access.as_ref().and_then(Claims::expires_at);
+        let needs_refresh = access.as_ref().is_none_or(|c| c.is_expired(REFRESH_SKEW));
+        let email = parse_claims(&self.tokens.id_token).and_then(|claims| claims.email);
+        let account_id = Some(self.tokens.account_id.clone()).filter(|id| !id.is_empty());
+
+        AuthStatus {
+            authenticated: access.is_some(),
+            expires_at,
+            account_id,
+            email,
+            needs_refresh,
+        }
+    }
+}
+
+/// What the stored credentials say about the login, decoded from the JWTs.
+#[derive(Debug, Clone)]
+pub struct AuthStatus {
+    /// The access token could be decoded.
+    pub authenticated: bool,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub account_id: Option<String>,
+    pub email: Option<String>,
+    pub needs_refresh: bool,
+}
+
+impl AuthStatus {
+    /// Human-readable account label, e.g. `me@example.com (account abc)`.
+    pub fn who(&self) -> String {
+        match (&self.email, &self.account_id) {
+            (Some(email), Some(id)) => format!("{} (account {})", email, id),
+            (Some(email), None) => email.clone(),
+            (None, Some(id)) => format!("account {}", id),
+            (None, None) => "unknown account".to_string(),
         }
-        true
     }
 }
 
-use crate::config::{CLIENT_ID, OAUTH_TOKEN_URL};
+use crate::config::{Config, CLIENT_ID};
+use crate::http;
+use crate::provider::timeout_hint;
 
-pub fn refresh_tokens(refresh_token: &str) -> Result<Tokens> {
-    let client = reqwest::blocking::Client::new();
+pub fn refresh_tokens(config: &Config, refresh_token: &str) -> Result<Tokens> {
+    let client = http::client()?;
 
     let payload = serde_json::json!({
         "grant_type": "refresh_token",
@@ -84,10 +197,11 @@ pub fn refresh_tokens(refresh_token: &str) -> Result<Tokens> {
     });
 
     let resp = client
-        .post(OAUTH_TOKEN_URL)
+        .post(config.oauth_token_url())
         .json(&payload)
-        .timeout(std::time::Duration::from_secs(30))
+        .timeout(config.auth_timeout())
         .send()
+        .map_err(|e| timeout_hint(e, "auth_timeout_secs"))
         .context("Failed to send refresh token request")?;
 
     if !resp.status().is_success() {
@@ -110,14 +224,8 @@ pub fn refresh_tokens(refresh_token: &str) -> Result<Tokens> {
         .to_string();
 
     // Extract account_id from id_token claims
-    let account_id = parse_jwt_claims(&id_token)
-        .and_then(|claims| {
-            claims
-                .get("https://api.openai.com/auth")
-                .and_then(|auth| auth.get("chatgpt_account_id"))
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        })
+    let account_id = parse_claims(&id_token)
+        .and_then(|claims| claims.chatgpt_account_id().map(str::to_string))
         .unwrap_or_default();
 
     Ok(Tokens {
@@ -128,22 +236,133 @@ pub fn refresh_tokens(refresh_token: &str) -> Result<Tokens> {
     })
 }
 
+/// Tokens from this process's latest refresh, and when it happened.
+static RECENT_REFRESH: Mutex<Option<(Tokens, Instant)>> = Mutex::new(None);
+
+/// How long a just-refreshed token is reused without checking again.
+const REFRESH_REUSE_WINDOW: Duration = Duration::from_secs(60);
+
 /// Get valid tokens, refreshing if necessary
-pub fn get_valid_tokens() -> Result<Option<Tokens>> {
+pub fn get_valid_tokens(config: &Config) -> Result<Option<Tokens>> {
     let auth = match AuthData::load()? {
         Some(auth) => auth,
         None => return Ok(None),
     };
 
-    if auth.needs_refresh() {
-        let new_tokens = refresh_tokens(&auth.tokens.refresh_token)?;
+    if !auth.needs_refresh(config) {
+        return Ok(Some(auth.tokens));
+    }
+
+    // Only one refresh at a time in this process; a caller that waited on
+    // the guard reuses the result instead of spending the refresh token again.
+    let mut recent = RECENT_REFRESH.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((tokens, at)) = recent.as_ref() {
+        if at.elapsed() < REFRESH_REUSE_WINDOW {
+            return Ok(Some(tokens.clone()));
+        }
+    }
+
+    // Another jose process may be refreshing right now. Wait for it, then
+    // re-read: refresh tokens rotate, so refreshing with a stale one (and
+    // saving over the newer one) would log the user out.
+    let _lock = AuthData::lock()?;
+    let auth = match AuthData::load()? {
+        Some(auth) => auth,
+        None => return Ok(None),
+    };
+
+    if auth.needs_refresh(config) {
+        let new_tokens = refresh_tokens(config, &auth.tokens.refresh_token)?;
         let new_auth = AuthData {
             tokens: new_tokens.clone(),
             last_refresh: chrono::Utc::now().to_rfc3339(),
         };
         new_auth.save()?;
+        *recent = Some((new_tokens.clone(), Instant::now()));
         Ok(Some(new_tokens))
     } else {
         Ok(Some(auth.tokens))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::ffi::OsString;
+
+    /// Serializes tests that repoint `HOME` / `XDG_DATA_HOME`.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Point the data directory (and `HOME`, so no real `~/.jose` is
+    /// migrated) at a fresh temp dir until dropped.
+    struct TempHome {
+        dir: PathBuf,
+        saved: Vec<(&'static str, Option<OsString>)>,
+    }
+
+    impl TempHome {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("jose-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let saved = ["HOME", "XDG_DATA_HOME"]
+                .into_iter()
+                .map(|var| (var, env::var_os(var)))
+                .collect();
+            env::set_var("HOME", &dir);
+            env::set_var("XDG_DATA_HOME", dir.join("data"));
+            Self { dir, saved }
+        }
+    }
+
+    impl Drop for TempHome {
+        fn drop(&mut self) {
+            for (var, value) in &self.saved {
+                match value {
+                    Some(value) => env::set_var(var, value),
+                    None => env::remove_var(var),
+                }
+            }
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn save_then_load_round_trips() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let home = TempHome::new("auth");
+
+        assert!(AuthData::load().unwrap().is_none());
+        let auth = AuthData {
+            tokens: Tokens {
+                id_token: "id".to_string(),
+                access_token: "access".to_string(),
+                refresh_token: "refresh".to_string(),
+                account_id: "acct".to_string(),
+            },
+            last_refresh: "2026-01-01T00:00:00Z".to_string(),
+        };
+        auth.save().unwrap();
+        // Saving again replaces the file rather than failing on it.
+        auth.save().unwrap();
+
+        let loaded = AuthData::load().unwrap().unwrap();
+        assert_eq!(loaded.tokens, auth.tokens);
+        assert_eq!(loaded.last_refresh, auth.last_refresh);
+
+        let path = home.dir.join("data/jose/auth.json");
+        assert!(path.exists());
+        let leftovers = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name() != "auth.json")
+            .count();
+        assert_eq!(leftovers, 0, "temp file left behind");
+
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
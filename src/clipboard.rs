@@ -1,7 +1,131 @@
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
 use arboard::Clipboard;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::config::ClipboardTarget;
+
+/// Copy `text` with `arboard`, falling back to a clipboard tool on `PATH`
+/// and then to an OSC 52 escape (e.g. over SSH). `JOSE_OSC52=1` goes
+/// straight to OSC 52. If everything fails, the `arboard` error is returned.
+pub fn copy_to_clipboard(text: &str, target: ClipboardTarget) -> Result<()> {
+    if std::env::var("JOSE_OSC52").is_ok_and(|v| v == "1") {
+        return osc52(text, target);
+    }
+    let err = match Clipboard::new() {
+        Ok(mut clipboard) => match set_text(&mut clipboard, text, target) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        },
+        Err(e) => e,
+    };
+    if copy_with_tool(text, target) || osc52(text, target).is_ok() {
+        return Ok(());
+    }
+    Err(err.into())
+}
+
+/// External clipboard tools, as `(clipboard argv, primary argv)`. A tool
+/// without a primary selection has `None` there.
+#[cfg(target_os = "macos")]
+const TOOLS: &[(&[&str], Option<&[&str]>)] = &[(&["pbcopy"], None)];
+
+#[cfg(not(target_os = "macos"))]
+const TOOLS: &[(&[&str], Option<&[&str]>)] = &[
+    (&["wl-copy"], Some(&["wl-copy", "--primary"])),
+    (
+        &["xclip", "-selection", "clipboard"],
+        Some(&["xclip", "-selection", "primary"]),
+    ),
+    (
+        &["xsel", "--clipboard", "--input"],
+        Some(&["xsel", "--primary", "--input"]),
+    ),
+];
+
+/// Try each tool in [`TOOLS`] until one accepts the text on stdin.
+fn copy_with_tool(text: &str, target: ClipboardTarget) -> bool {
+    TOOLS.iter().any(|&(clipboard, primary)| {
+        let argvs: Vec<&[&str]> = match target {
+            ClipboardTarget::Clipboard => vec![clipboard],
+            ClipboardTarget::Primary => vec![primary.unwrap_or(clipboard)],
+            ClipboardTarget::Both => std::iter::once(clipboard).chain(primary).collect(),
+        };
+        argvs.iter().all(|argv| pipe_to(argv, text).is_ok())
+    })
+}
 
-pub fn copy_to_clipboard(text: &str) -> Result<(), arboard::Error> {
-    let mut clipboard = Clipboard::new()?;
-    clipboard.set_text(text)?;
+fn pipe_to(argv: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!("{} failed", argv[0]))),
+    }
+}
+
+/// Ask the terminal to set its clipboard. The escape goes to stderr, and
+/// only when that is a terminal, so it never ends up in piped output.
+fn osc52(text: &str, target: ClipboardTarget) -> Result<()> {
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        anyhow::bail!("OSC 52 needs a terminal on stderr");
+    }
+    let selection = match target {
+        ClipboardTarget::Clipboard => "c",
+        ClipboardTarget::Primary => "p",
+        ClipboardTarget::Both => "cp",
+    };
+    let mut seq = format!("\x1b]52;{};{}\x07", selection, STANDARD.encode(text));
+    // tmux swallows OSC 52 unless it is wrapped in a passthrough sequence.
+    if std::env::var_os("TMUX").is_some() {
+        seq = format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b"));
+    }
+    stderr.write_all(seq.as_bytes())?;
+    stderr.flush()?;
+    Ok(())
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+fn set_text(
+    clipboard: &mut Clipboard,
+    text: &str,
+    target: ClipboardTarget,
+) -> Result<(), arboard::Error> {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+
+    let kinds: &[LinuxClipboardKind] = match target {
+        ClipboardTarget::Clipboard => &[LinuxClipboardKind::Clipboard],
+        ClipboardTarget::Primary => &[LinuxClipboardKind::Primary],
+        ClipboardTarget::Both => &[LinuxClipboardKind::Clipboard, LinuxClipboardKind::Primary],
+    };
+    for &kind in kinds {
+        clipboard.set().clipboard(kind).text(text)?;
+    }
     Ok(())
 }
+
+/// Only Linux has a primary selection; everything goes to the clipboard elsewhere.
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+)))]
+fn set_text(
+    clipboard: &mut Clipboard,
+    text: &str,
+    _target: ClipboardTarget,
+) -> Result<(), arboard::Error> {
+    clipboard.set_text(text)
+}
@@ -1,7 +1,12 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::paths;
+use crate::shell::ShellType;
 
 /// OAuth configuration (same as Codex CLI)
 pub const CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
@@ -12,15 +17,94 @@ pub const CHATGPT_RESPONSES_URL: &str = "https://chatgpt.com/backend-api/codex/r
 /// Must use port 1455 - this is the only port registered with OpenAI's OAuth
 pub const OAUTH_PORT: u16 = 1455;
 
-/// Models known to the ChatGPT subscription backend (per OpenAI Codex docs).
-/// Only used for the `chatgpt` provider; openai-compatible models are free-form.
-pub const AVAILABLE_MODELS: &[&str] = &[
-    "gpt-5.5",
-    "gpt-5.4",
-    "gpt-5.4-mini",
-    "gpt-5.3-codex-spark",
+/// Static metadata for a known model, shown by `jose model info`.
+pub struct KnownModel {
+    pub name: &'static str,
+    pub context_window: u64,
+    pub streaming: bool,
+    pub tools: bool,
+    pub reasoning: bool,
+    pub vision: bool,
+    pub recommended_for: &'static str,
+}
+
+/// Models known to the ChatGPT subscription backend (per OpenAI Codex docs),
+/// with their metadata. The name list only applies to the `chatgpt` provider;
+/// openai-compatible models are free-form. Entries can be overridden (or
+/// added for openai-compatible models) via `model_info` in the config file.
+pub const KNOWN_MODELS: &[KnownModel] = &[
+    KnownModel {
+        name: "gpt-5.5",
+        context_window: 400_000,
+        streaming: true,
+        tools: true,
+        reasoning: true,
+        vision: true,
+        recommended_for: "hardest multi-step tasks; slowest and most expensive",
+    },
+    KnownModel {
+        name: "gpt-5.4",
+        context_window: 400_000,
+        streaming: true,
+        tools: true,
+        reasoning: true,
+        vision: true,
+        recommended_for: "tricky pipelines and scripts where accuracy matters",
+    },
+    KnownModel {
+        name: "gpt-5.4-mini",
+        context_window: 400_000,
+        streaming: true,
+        tools: true,
+        reasoning: true,
+        vision: true,
+        recommended_for: "everyday one-liners; fast and cheap (default)",
+    },
+    KnownModel {
+        name: "gpt-5.3-codex-spark",
+        context_window: 128_000,
+        streaming: true,
+        tools: true,
+        reasoning: true,
+        vision: false,
+        recommended_for: "near-instant answers for simple commands",
+    },
 ];
 
+/// Names of the [`KNOWN_MODELS`], in table order.
+pub fn known_model_names() -> impl Iterator<Item = &'static str> {
+    KNOWN_MODELS.iter().map(|m| m.name)
+}
+
+/// Per-model overrides for [`KNOWN_MODELS`]; unset fields keep the built-in value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelInfoOverride {
+    #[serde(default)]
+    pub context_window: Option<u64>,
+    #[serde(default)]
+    pub streaming: Option<bool>,
+    #[serde(default)]
+    pub tools: Option<bool>,
+    #[serde(default)]
+    pub reasoning: Option<bool>,
+    #[serde(default)]
+    pub vision: Option<bool>,
+    #[serde(default)]
+    pub recommended_for: Option<String>,
+}
+
+/// Resolved model metadata: built-in table merged with config overrides.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub context_window: Option<u64>,
+    pub streaming: Option<bool>,
+    pub tools: Option<bool>,
+    pub reasoning: Option<bool>,
+    pub vision: Option<bool>,
+    pub recommended_for: Option<String>,
+}
+
 /// Default model: a fast, low-cost mini model.
 pub const DEFAULT_MODEL: &str = "gpt-5.4-mini";
 
@@ -45,6 +129,40 @@ impl ProviderKind {
     }
 }
 
+/// How hard gpt-5 models think before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+/// Which selection(s) the generated command is copied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardTarget {
+    /// The regular Ctrl+V clipboard (default).
+    #[default]
+    Clipboard,
+    /// The X11/Wayland primary selection (middle-click paste). Linux only.
+    Primary,
+    /// Both of the above.
+    Both,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -56,6 +174,117 @@ pub struct Config {
     /// Optional API key for openai-compatible provider.
     #[serde(default)]
     pub api_key: Option<String>,
+    /// Models that require an explicit confirmation (or `--yes`) before querying.
+    #[serde(default)]
+    pub confirm_models: Vec<String>,
+    /// Strip shell-prompt prefixes like `$ ` or `PS> ` from generated commands.
+    #[serde(default = "default_true")]
+    pub strip_prompt_prefix: bool,
+    /// Drop leading filler like "Sure! Here's the command:" from responses.
+    /// Off by default since the detection is heuristic.
+    #[serde(default)]
+    pub trim_acknowledgements: bool,
+    /// Text always prepended to the system instructions (e.g. a data-handling
+    /// notice required by a compliance team).
+    #[serde(default)]
+    pub mandatory_preamble: Option<String>,
+    /// Template replacing the built-in command-generation prompt, with
+    /// `{os}`, `{shell}`, `{arch}`, `{coreutils}` and `{env}` placeholders.
+    #[serde(default)]
+    pub command_system_prompt: Option<String>,
+    /// Column limit for printed prose (0 = no wrapping). Commands never wrap.
+    #[serde(default)]
+    pub wrap_width: usize,
+    /// Model to retry with once if the primary model times out or is unavailable.
+    #[serde(default)]
+    pub fallback_model: Option<String>,
+    /// Overrides/additions to the built-in model metadata, keyed by model name.
+    #[serde(default)]
+    pub model_info: HashMap<String, ModelInfoOverride>,
+    /// Reasoning effort for gpt-5 models (`minimal`, `low`, `medium`, `high`);
+    /// the model's default when unset. Ignored for other models.
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Selection to copy commands to; `primary` falls back to the clipboard off Linux.
+    #[serde(default)]
+    pub clipboard_target: ClipboardTarget,
+    /// Paths (e.g. `/etc`, `~/.ssh`) whose commands need confirmation before
+    /// they are copied or run.
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+    /// Ask the model whether a generated command is destructive, and confirm
+    /// before copying it if so (interactive use only).
+    #[serde(default = "default_true")]
+    pub safety_check: bool,
+    /// Retries for transient API failures (429, 5xx) before giving up.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Verify the access token's signature against the issuer's JWKS before
+    /// trusting it (opt-in; needs network the first time each day).
+    #[serde(default)]
+    pub verify_jwt: bool,
+    /// Shell to generate commands for (e.g. `zsh`, `pwsh`), instead of detecting it.
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Timeout for model requests, in seconds.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Timeout for OAuth token exchange and refresh, in seconds.
+    #[serde(default = "default_auth_timeout_secs")]
+    pub auth_timeout_secs: u64,
+    /// How long `jose login` waits for the browser to come back, in seconds.
+    #[serde(default = "default_login_timeout_secs")]
+    pub login_timeout_secs: u64,
+    /// Stream ChatGPT responses over SSE. Turn off behind proxies that buffer
+    /// or cut long-lived connections; the answer then arrives in one piece.
+    #[serde(default = "default_true")]
+    pub streaming: bool,
+    /// ChatGPT Responses endpoint, e.g. a local proxy (`JOSE_RESPONSES_URL`).
+    #[serde(default)]
+    pub responses_url: Option<String>,
+    /// OAuth issuer (`JOSE_OAUTH_ISSUER`).
+    #[serde(default)]
+    pub oauth_issuer: Option<String>,
+    /// OAuth token endpoint (`JOSE_OAUTH_TOKEN_URL`); follows the issuer if unset.
+    #[serde(default)]
+    pub oauth_token_url: Option<String>,
+    /// Also append timestamped log messages to this file.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Rotate the log file once it reaches this size (0 = never rotate).
+    #[serde(default = "default_log_max_bytes")]
+    pub log_max_bytes: u64,
+    /// Number of rotated log files (`<log_file>.1` ..) to keep.
+    #[serde(default = "default_log_keep")]
+    pub log_keep: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_request_timeout_secs() -> u64 {
+    120
+}
+
+fn default_auth_timeout_secs() -> u64 {
+    30
+}
+
+fn default_login_timeout_secs() -> u64 {
+    300
+}
+
+fn default_log_max_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_log_keep() -> usize {
+    3
 }
 
 impl Default for Config {
@@ -65,21 +294,123 @@ impl Default for Config {
             default_model: DEFAULT_MODEL.to_string(),
             base_url: None,
             api_key: None,
+            confirm_models: Vec::new(),
+            strip_prompt_prefix: true,
+            trim_acknowledgements: false,
+            mandatory_preamble: None,
+            command_system_prompt: None,
+            wrap_width: 0,
+            fallback_model: None,
+            model_info: HashMap::new(),
+            reasoning_effort: None,
+            clipboard_target: ClipboardTarget::default(),
+            protected_paths: Vec::new(),
+            safety_check: true,
+            max_retries: default_max_retries(),
+            verify_jwt: false,
+            shell: None,
+            request_timeout_secs: default_request_timeout_secs(),
+            auth_timeout_secs: default_auth_timeout_secs(),
+            login_timeout_secs: default_login_timeout_secs(),
+            streaming: true,
+            responses_url: None,
+            oauth_issuer: None,
+            oauth_token_url: None,
+            log_file: None,
+            log_max_bytes: default_log_max_bytes(),
+            log_keep: default_log_keep(),
         }
     }
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
+        let config = Self::load_unvalidated()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read the config without [`Self::validate`], so `jose config` can still
+    /// repair a bad value.
+    pub fn load_unvalidated() -> Result<Self> {
         let path = Self::config_path()?;
-        if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            Ok(serde_json::from_str(&content)?)
-        } else {
-            Ok(Self::default())
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Names of all settings, as they appear in `config.json`.
+    pub fn keys() -> Vec<String> {
+        match serde_json::to_value(Self::default()) {
+            Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+            _ => Vec::new(),
         }
     }
 
+    /// Current value of the setting `key` (`null` when unset).
+    pub fn get(&self, key: &str) -> Result<serde_json::Value> {
+        let serde_json::Value::Object(mut map) = serde_json::to_value(self)? else {
+            unreachable!("Config serializes to an object");
+        };
+        map.remove(key).ok_or_else(|| unknown_key(key))
+    }
+
+    /// Set `key` from its command-line form: JSON (`true`, `30`, `null`,
+    /// `["a"]`), or else a plain string. The result must still be a valid
+    /// config.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let serde_json::Value::Object(mut map) = serde_json::to_value(&*self)? else {
+            unreachable!("Config serializes to an object");
+        };
+        if !map.contains_key(key) {
+            return Err(unknown_key(key));
+        }
+        let string = serde_json::Value::String(value.to_string());
+        let candidates = match serde_json::from_str(value) {
+            Ok(json) => vec![json, string],
+            Err(_) => vec![string],
+        };
+        let mut last_err = None;
+        for candidate in candidates {
+            map.insert(key.to_string(), candidate);
+            match serde_json::from_value::<Config>(serde_json::Value::Object(map.clone())) {
+                Ok(config) => {
+                    config.validate()?;
+                    *self = config;
+                    return Ok(());
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+        let err = last_err.expect("at least one candidate");
+        Err(anyhow::anyhow!("Invalid value for `{}`: {}", key, err))
+    }
+
+    /// Reject endpoint overrides that aren't absolute http(s) URLs, and a
+    /// `shell` jose doesn't know.
+    fn validate(&self) -> Result<()> {
+        if let Some(shell) = &self.shell {
+            if let Err(e) = shell.parse::<ShellType>() {
+                anyhow::bail!("Invalid `shell` in config: {}", e);
+            }
+        }
+        let endpoints = [
+            ("responses_url", self.responses_url()),
+            ("oauth_issuer", self.oauth_issuer()),
+            ("oauth_token_url", self.oauth_token_url()),
+        ];
+        for (name, url) in endpoints {
+            let valid = reqwest::Url::parse(&url)
+                .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.has_host());
+            if !valid {
+                anyhow::bail!("Invalid `{}` in config or environment: {}", name, url);
+            }
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path()?;
         if let Some(parent) = path.parent() {
@@ -104,9 +435,93 @@ impl Config {
             .or_else(|| self.api_key.clone())
     }
 
-    fn config_path() -> Result<PathBuf> {
-        let home =
-            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        Ok(home.join(".jose").join("config.json"))
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn auth_timeout(&self) -> Duration {
+        Duration::from_secs(self.auth_timeout_secs)
+    }
+
+    pub fn login_timeout(&self) -> Duration {
+        Duration::from_secs(self.login_timeout_secs)
     }
+
+    /// ChatGPT Responses endpoint, env (`JOSE_RESPONSES_URL`) taking
+    /// precedence over the config file.
+    pub fn responses_url(&self) -> String {
+        std::env::var("JOSE_RESPONSES_URL")
+            .ok()
+            .or_else(|| self.responses_url.clone())
+            .unwrap_or_else(|| CHATGPT_RESPONSES_URL.to_string())
+    }
+
+    /// OAuth issuer, env (`JOSE_OAUTH_ISSUER`) taking precedence over the config file.
+    pub fn oauth_issuer(&self) -> String {
+        std::env::var("JOSE_OAUTH_ISSUER")
+            .ok()
+            .or_else(|| self.oauth_issuer.clone())
+            .unwrap_or_else(|| OAUTH_ISSUER.to_string())
+    }
+
+    /// OAuth token endpoint, env (`JOSE_OAUTH_TOKEN_URL`) taking precedence.
+    /// When only the issuer is overridden, the token endpoint follows it.
+    pub fn oauth_token_url(&self) -> String {
+        if let Some(url) = std::env::var("JOSE_OAUTH_TOKEN_URL")
+            .ok()
+            .or_else(|| self.oauth_token_url.clone())
+        {
+            return url;
+        }
+        let issuer = self.oauth_issuer();
+        if issuer == OAUTH_ISSUER {
+            OAUTH_TOKEN_URL.to_string()
+        } else {
+            format!("{}/oauth/token", issuer.trim_end_matches('/'))
+        }
+    }
+
+    /// Metadata for `model`, or `None` if neither the built-in table nor the
+    /// config knows it.
+    pub fn model_info(&self, model: &str) -> Option<ModelInfo> {
+        let known = KNOWN_MODELS.iter().find(|m| m.name == model);
+        let overrides = self.model_info.get(model);
+        if known.is_none() && overrides.is_none() {
+            return None;
+        }
+
+        let mut info = ModelInfo {
+            name: model.to_string(),
+            ..Default::default()
+        };
+        if let Some(m) = known {
+            info.context_window = Some(m.context_window);
+            info.streaming = Some(m.streaming);
+            info.tools = Some(m.tools);
+            info.reasoning = Some(m.reasoning);
+            info.vision = Some(m.vision);
+            info.recommended_for = Some(m.recommended_for.to_string());
+        }
+        if let Some(o) = overrides {
+            info.context_window = o.context_window.or(info.context_window);
+            info.streaming = o.streaming.or(info.streaming);
+            info.tools = o.tools.or(info.tools);
+            info.reasoning = o.reasoning.or(info.reasoning);
+            info.vision = o.vision.or(info.vision);
+            info.recommended_for = o.recommended_for.clone().or(info.recommended_for);
+        }
+        Some(info)
+    }
+
+    pub fn config_path() -> Result<PathBuf> {
+        paths::config_file("config.json")
+    }
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Unknown config key `{}`. Valid keys: {}",
+        key,
+        Config::keys().join(", ")
+    )
 }
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::paths;
+
+/// One query and what the model answered, stored as a line of `history.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: String,
+    pub model: String,
+    pub prompt: String,
+    pub commands: Vec<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(model: &str, prompt: &str, commands: &[String]) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+            commands: commands.to_vec(),
+        }
+    }
+}
+
+/// Append `entry` to the history file.
+///
+/// The line is written with a single append-mode write, so concurrent `jose`
+/// processes interleave whole entries rather than clobbering each other.
+pub fn append(entry: &HistoryEntry) -> Result<()> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// The last `limit` entries, oldest first. Unparseable lines are skipped.
+pub fn load(limit: usize) -> Result<Vec<HistoryEntry>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    let entries: Vec<HistoryEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// Truncate the history file.
+pub fn clear() -> Result<()> {
+    let path = history_path()?;
+    if path.exists() {
+        fs::write(&path, "")?;
+    }
+    Ok(())
+}
+
+fn history_path() -> Result<PathBuf> {
+    paths::data_file("history.jsonl")
+}
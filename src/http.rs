@@ -0,0 +1,39 @@
+//! The one place HTTP clients are built, so every request honours the same
+//! network settings:
+//!
+//! - `HTTPS_PROXY` / `HTTP_PROXY` / `ALL_PROXY` (and `NO_PROXY`) are picked up
+//!   by reqwest's system-proxy support.
+//! - `JOSE_CA_BUNDLE` names a PEM file of extra root certificates, for
+//!   TLS-intercepting corporate proxies.
+
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use reqwest::Certificate;
+use std::sync::OnceLock;
+
+/// The process-wide client. Built on first use and then shared (clones are
+/// cheap handles), so a token refresh, the request and any retries reuse
+/// the same keep-alive connections.
+pub fn client() -> Result<Client> {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = build()?;
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+fn build() -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(path) = std::env::var_os("JOSE_CA_BUNDLE") {
+        let path = std::path::PathBuf::from(path);
+        let pem = std::fs::read(&path)
+            .with_context(|| format!("Failed to read JOSE_CA_BUNDLE {}", path.display()))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid PEM in JOSE_CA_BUNDLE {}", path.display()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder.build().context("Failed to build HTTP client")
+}
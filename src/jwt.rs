@@ -1,7 +1,20 @@
+use anyhow::{Context, Result};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::signature::{RsaPublicKeyComponents, RSA_PKCS1_2048_8192_SHA256};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
-/// Parse JWT claims from a token (without verification)
+use crate::config::Config;
+use crate::http;
+use crate::paths;
+
+/// Parse JWT claims from a token (without verification).
+///
+/// Only for display and expiry heuristics: a tampered token decodes just as
+/// well. Use [`verify_jwt`] when the claims must be trusted.
 pub fn parse_jwt_claims(token: &str) -> Option<Value> {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 3 {
@@ -12,3 +25,179 @@ pub fn parse_jwt_claims(token: &str) -> Option<Value> {
     let decoded = URL_SAFE_NO_PAD.decode(payload).ok()?;
     serde_json::from_slice(&decoded).ok()
 }
+
+/// The claims jose reads from ChatGPT tokens. Unverified, like
+/// [`parse_jwt_claims`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Claims {
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub iss: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(rename = "https://api.openai.com/auth", default)]
+    openai_auth: Option<OpenAiAuthClaims>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OpenAiAuthClaims {
+    #[serde(default)]
+    chatgpt_account_id: Option<String>,
+}
+
+impl Claims {
+    pub fn chatgpt_account_id(&self) -> Option<&str> {
+        self.openai_auth.as_ref()?.chatgpt_account_id.as_deref()
+    }
+
+    pub fn expires_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.exp?, 0)
+    }
+
+    /// Whether the token expires within `skew` from now. A token without
+    /// `exp` counts as expired.
+    pub fn is_expired(&self, skew: Duration) -> bool {
+        let skew = chrono::Duration::from_std(skew).unwrap_or(chrono::Duration::MAX);
+        self.expires_at()
+            .is_none_or(|exp| exp <= chrono::Utc::now() + skew)
+    }
+}
+
+/// [`parse_jwt_claims`] into the typed [`Claims`].
+pub fn parse_claims(token: &str) -> Option<Claims> {
+    serde_json::from_value(parse_jwt_claims(token)?).ok()
+}
+
+/// A JSON Web Key Set, as served at `{issuer}/.well-known/jwks.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwk {
+    #[serde(default)]
+    pub kid: Option<String>,
+    pub kty: String,
+    #[serde(default)]
+    pub n: Option<String>,
+    #[serde(default)]
+    pub e: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+/// Check `token`'s RS256 signature against `jwks`, then its `exp`, `iss`
+/// and `aud` claims. Returns the verified claims.
+pub fn verify_jwt(token: &str, jwks: &Jwks, issuer: &str, audience: &str) -> Result<Value> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("Malformed JWT");
+    };
+
+    let header: Header =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header)?).context("Invalid JWT header")?;
+    if header.alg != "RS256" {
+        anyhow::bail!("Unsupported JWT algorithm {}", header.alg);
+    }
+    let key = jwks
+        .keys
+        .iter()
+        .filter(|k| k.kty == "RSA")
+        .find(|k| header.kid.is_none() || k.kid == header.kid)
+        .ok_or_else(|| anyhow::anyhow!("No JWKS key matches the token's key id"))?;
+    let (Some(n), Some(e)) = (&key.n, &key.e) else {
+        anyhow::bail!("JWKS key is missing its RSA modulus or exponent");
+    };
+    let public_key = RsaPublicKeyComponents {
+        n: URL_SAFE_NO_PAD.decode(n)?,
+        e: URL_SAFE_NO_PAD.decode(e)?,
+    };
+    let signed = &token[..token.len() - signature.len() - 1];
+    public_key
+        .verify(
+            &RSA_PKCS1_2048_8192_SHA256,
+            signed.as_bytes(),
+            &URL_SAFE_NO_PAD.decode(signature)?,
+        )
+        .map_err(|_| anyhow::anyhow!("JWT signature does not verify"))?;
+
+    let claims: Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?).context("Invalid JWT payload")?;
+    let typed: Claims = serde_json::from_value(claims.clone()).context("Invalid JWT claims")?;
+    if typed.is_expired(Duration::ZERO) {
+        anyhow::bail!("JWT has expired");
+    }
+    let iss = typed.iss.as_deref().unwrap_or_default();
+    if iss.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        anyhow::bail!("JWT issuer `{}` is not `{}`", iss, issuer);
+    }
+    // `aud` may be a single string or a list.
+    let aud_ok = match claims.get("aud") {
+        Some(Value::String(aud)) => aud == audience,
+        Some(Value::Array(auds)) => auds.iter().any(|a| a == audience),
+        _ => false,
+    };
+    if !aud_ok {
+        anyhow::bail!("JWT audience does not include `{}`", audience);
+    }
+    Ok(claims)
+}
+
+/// How long a downloaded JWKS is trusted before it is fetched again.
+const JWKS_CACHE_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct CachedJwks {
+    fetched_at: i64,
+    issuer: String,
+    jwks: Jwks,
+}
+
+/// The issuer's signing keys, from `~/.jose/jwks.json` when fresh, otherwise
+/// downloaded and cached there.
+pub fn load_jwks(config: &Config) -> Result<Jwks> {
+    let issuer = config.oauth_issuer();
+    let path = jwks_cache_path()?;
+    let now = chrono::Utc::now().timestamp();
+    if let Ok(content) = fs::read_to_string(&path) {
+        if let Ok(cached) = serde_json::from_str::<CachedJwks>(&content) {
+            if cached.issuer == issuer && now - cached.fetched_at < JWKS_CACHE_SECS {
+                return Ok(cached.jwks);
+            }
+        }
+    }
+
+    let url = format!("{}/.well-known/jwks.json", issuer.trim_end_matches('/'));
+    let jwks: Jwks = http::client()?
+        .get(&url)
+        .timeout(config.auth_timeout())
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .with_context(|| format!("Failed to fetch {}", url))?
+        .json()
+        .context("Invalid JWKS")?;
+
+    let cached = CachedJwks {
+        fetched_at: now,
+        issuer,
+        jwks,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&cached)?)?;
+    Ok(cached.jwks)
+}
+
+fn jwks_cache_path() -> Result<PathBuf> {
+    paths::data_file("jwks.json")
+}
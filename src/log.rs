@@ -1,6 +1,13 @@
 //! Cross-platform logging utilities with colored output
 
+use std::borrow::Cow;
+use std::fs::{self, File};
 use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 /// ANSI color codes
 pub mod colors {
@@ -13,16 +20,47 @@ pub mod colors {
     pub const DIM: &str = "\x1b[2m";
 }
 
+/// Cleared when the console can't interpret ANSI escapes (old Windows hosts).
+static ANSI: AtomicBool = AtomicBool::new(true);
+
+/// Prepare the terminal for ANSI output. On Windows this turns on virtual
+/// terminal processing, without which cmd.exe and older PowerShell hosts
+/// print escapes as garbage; if that fails, colors are disabled.
+pub fn init_terminal() {
+    #[cfg(windows)]
+    if enable_ansi_support::enable_ansi_support().is_err() {
+        ANSI.store(false, Ordering::Relaxed);
+    }
+}
+
+fn ansi() -> bool {
+    ANSI.load(Ordering::Relaxed)
+}
+
 /// Check if stdout supports colors
 fn supports_color() -> bool {
     // Check NO_COLOR environment variable (https://no-color.org/)
     if std::env::var("NO_COLOR").is_ok() {
         return false;
     }
+    // FORCE_COLOR / CLICOLOR_FORCE keep colors when piping into `less -R`
+    if force_color() {
+        return true;
+    }
+    if !ansi() {
+        return false;
+    }
     // Check if stdout is a terminal
     io::stdout().is_terminal()
 }
 
+/// `FORCE_COLOR` or `CLICOLOR_FORCE` is set to anything but `0`/`false`.
+fn force_color() -> bool {
+    ["FORCE_COLOR", "CLICOLOR_FORCE"]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|v| !matches!(v.as_str(), "0" | "false")))
+}
+
 /// Format text with color if supported
 fn colorize(text: &str, color: &str) -> String {
     if supports_color() {
@@ -32,39 +70,497 @@ fn colorize(text: &str, color: &str) -> String {
     }
 }
 
+/// Column limit for prose output; 0 disables wrapping. Commands never wrap.
+static WRAP_WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the column limit used to word-wrap log messages (0 = no wrapping).
+pub fn set_wrap_width(width: usize) {
+    WRAP_WIDTH.store(width, Ordering::Relaxed);
+}
+
+/// When set, nothing decorative is printed to stdout (warnings and errors
+/// still go to stderr), leaving stdout to machine-readable output.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress stdout log output, e.g. for `--json`.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed) || level() == Level::Quiet
+}
+
+/// How chatty logging is, from `-q`/`-v`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Errors (and the generated command) only.
+    Quiet,
+    Normal,
+    /// Also [`debug`] lines: endpoints, models, token expiry.
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Level::Quiet,
+        1 => Level::Normal,
+        _ => Level::Verbose,
+    }
+}
+
+/// Optional persistent copy of info/success/warn/error messages.
+static LOG_FILE: Mutex<Option<FileSink>> = Mutex::new(None);
+
+/// Append-only log file, rotated to `<path>.1` .. `<path>.<keep>` once it
+/// grows past `max_bytes`.
+struct FileSink {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl FileSink {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.max_bytes > 0 && self.size >= self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1` (dropping the oldest) and start afresh.
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(self.keep));
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(rotated(n), rotated(n + 1));
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+        *self = Self::open(&self.path, self.max_bytes, self.keep)?;
+        Ok(())
+    }
+}
+
+/// Also write log messages, timestamped, to `path`.
+pub fn set_log_file(path: &Path, max_bytes: u64, keep: usize) -> io::Result<()> {
+    let sink = FileSink::open(path, max_bytes, keep)?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+    Ok(())
+}
+
+/// Append a message to the log file, if one is configured. Failures are
+/// ignored: the file is a diagnostic aid and must never break a command.
+fn to_file(level: &str, message: &str) {
+    let mut guard = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = guard.as_mut() {
+        let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+        let _ = sink.write(&format!("{} {:<5} {}\n", timestamp, level, message));
+    }
+}
+
+/// Word-wrap `text` to `width` columns, prefixing continuation lines with
+/// `indent`. Existing line breaks are kept; words longer than a line are
+/// left whole rather than split (URLs must stay clickable).
+pub fn wrap(text: &str, width: usize, indent: &str) -> String {
+    let mut out = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            out.push_str(indent);
+        }
+        let mut col = 0;
+        for word in line.split_whitespace() {
+            let len = word.chars().count();
+            if col > 0 && col + 1 + len > width {
+                out.push('\n');
+                out.push_str(indent);
+                col = 0;
+            } else if col > 0 {
+                out.push(' ');
+                col += 1;
+            }
+            out.push_str(word);
+            col += len;
+        }
+    }
+    out
+}
+
+/// Apply the configured wrap width to a message; `indent` is both the width
+/// of the prefix printed before it and the prefix of continuation lines.
+fn wrap_message<'a>(message: &'a str, indent: &str) -> Cow<'a, str> {
+    match WRAP_WIDTH.load(Ordering::Relaxed) {
+        0 => Cow::Borrowed(message),
+        width => Cow::Owned(wrap(message, width.saturating_sub(indent.len()), indent)),
+    }
+}
+
 /// Log an info message
 pub fn info(message: &str) {
+    to_file("INFO", message);
+    if quiet() {
+        return;
+    }
+    let message = wrap_message(message, "    ");
     println!("{} {}", colorize("[*]", colors::CYAN), message);
 }
 
 /// Log a success message
 pub fn success(message: &str) {
+    to_file("INFO", message);
+    if quiet() {
+        return;
+    }
+    let message = wrap_message(message, "    ");
     println!("{} {}", colorize("[+]", colors::GREEN), message);
 }
 
 /// Log a warning message
 pub fn warn(message: &str) {
+    to_file("WARN", message);
+    if level() == Level::Quiet {
+        return;
+    }
+    let message = wrap_message(message, "    ");
     eprintln!("{} {}", colorize("[!]", colors::YELLOW), message);
 }
 
 /// Log an error message
 pub fn error(message: &str) {
+    to_file("ERROR", message);
+    let message = wrap_message(message, "    ");
     eprintln!("{} {}", colorize("[-]", colors::RED), message);
 }
 
 /// Log a debug/dim message
 pub fn dim(message: &str) {
-    println!("{}", colorize(message, colors::DIM));
+    if quiet() {
+        return;
+    }
+    println!("{}", colorize(&wrap_message(message, ""), colors::DIM));
+}
+
+/// Log a diagnostic line to stderr, only with `--verbose`
+pub fn debug(message: &str) {
+    if level() < Level::Verbose {
+        return;
+    }
+    to_file("DEBUG", message);
+    let message = wrap_message(message, "    ");
+    eprintln!("{} {}", colorize("[.]", colors::DIM), message);
 }
 
-/// Print a command (highlighted)
+/// Print plain prose (no prefix), wrapped to the configured width
+pub fn prose(text: &str) {
+    println!("{}", wrap_message(text, ""));
+}
+
+/// Print an indented bullet point, wrapped to the configured width
+pub fn bullet(text: &str) {
+    println!("    - {}", wrap_message(text, "      "));
+}
+
+/// Print a command (highlighted), indenting every line of multi-line commands
 pub fn command(cmd: &str) {
-    println!("    {}", colorize(cmd, colors::BOLD));
+    if quiet() {
+        return;
+    }
+    for line in cmd.lines() {
+        println!("    {}", colorize(line, colors::BOLD));
+    }
+}
+
+/// Print a numbered choice, indenting continuation lines of multi-line commands
+pub fn numbered(n: usize, cmd: &str) {
+    for (i, line) in cmd.lines().enumerate() {
+        let label = if i == 0 {
+            format!("{:>3})", n)
+        } else {
+            String::new()
+        };
+        println!("{:<4} {}", label, colorize(line, colors::BOLD));
+    }
+}
+
+/// Dimmed, erasable live view of streamed text on stderr (a terminal only).
+///
+/// Printed lines are counted, including soft wraps at `$COLUMNS` (default
+/// 80), so [`Preview::clear`] can move the cursor back up and erase them.
+pub struct Preview {
+    enabled: bool,
+    width: usize,
+    col: usize,
+    lines: usize,
+}
+
+impl Preview {
+    pub fn new() -> Self {
+        let width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .filter(|&w: &usize| w > 0)
+            .unwrap_or(80);
+        Self {
+            enabled: io::stderr().is_terminal() && ansi() && !quiet(),
+            width,
+            col: 0,
+            lines: 0,
+        }
+    }
+
+    pub fn write(&mut self, text: &str) {
+        if !self.enabled {
+            return;
+        }
+        for c in text.chars() {
+            if c == '\n' {
+                self.lines += 1;
+                self.col = 0;
+            } else {
+                if self.col == self.width {
+                    self.lines += 1;
+                    self.col = 0;
+                }
+                self.col += 1;
+            }
+        }
+        let mut stderr = io::stderr();
+        let _ = if std::env::var("NO_COLOR").is_ok() {
+            write!(stderr, "{}", text)
+        } else {
+            write!(stderr, "{}{}{}", colors::DIM, text, colors::RESET)
+        };
+        let _ = stderr.flush();
+    }
+
+    /// Erase everything written so far.
+    pub fn clear(&mut self) {
+        if !self.enabled || (self.lines == 0 && self.col == 0) {
+            return;
+        }
+        let mut stderr = io::stderr();
+        if self.lines > 0 {
+            let _ = write!(stderr, "\x1b[{}F", self.lines);
+        }
+        let _ = write!(stderr, "\r\x1b[J");
+        let _ = stderr.flush();
+        self.lines = 0;
+        self.col = 0;
+    }
+}
+
+/// Animated "still working" indicator on stderr (a terminal only), drawn by
+/// a background thread until [`Spinner::stop`] or drop erases it.
+pub struct Spinner {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    const FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    const INTERVAL: Duration = Duration::from_millis(80);
+
+    pub fn start(message: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let enabled = io::stderr().is_terminal() && ansi() && !quiet();
+        let thread = enabled.then(|| {
+            let (stop, message) = (Arc::clone(&stop), message.to_string());
+            std::thread::spawn(move || {
+                let mut stderr = io::stderr();
+                let (on, off) = match std::env::var("NO_COLOR") {
+                    Ok(_) => ("", ""),
+                    Err(_) => (colors::CYAN, colors::RESET),
+                };
+                for frame in Self::FRAMES.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let _ = write!(stderr, "\r{}{}{} {}", on, frame, off, message);
+                    let _ = stderr.flush();
+                    std::thread::sleep(Self::INTERVAL);
+                }
+                let _ = write!(stderr, "\r\x1b[K");
+                let _ = stderr.flush();
+            })
+        });
+        Self { stop, thread }
+    }
+
+    /// Erase the spinner. Safe to call more than once.
+    pub fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 /// Print without newline and flush
-#[allow(dead_code)] // Utility function for future use
 pub fn print_inline(message: &str) {
     print!("{}", message);
     let _ = io::stdout().flush();
 }
+
+/// Print a question on stderr, so prompts never end up in piped stdout
+fn ask(question: &str) {
+    eprint!("{}", question);
+    let _ = io::stderr().flush();
+}
+
+/// Ask a yes/no question on stdin. Anything but `y`/`yes` counts as no.
+pub fn confirm(question: &str) -> bool {
+    ask(&format!(
+        "{} {} [y/N] ",
+        colorize("[?]", colors::YELLOW),
+        question
+    ));
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask for a number from 1 to `count` on stdin; an empty answer picks 1.
+/// Returns the zero-based index, or `None` for anything else.
+pub fn choose(question: &str, count: usize) -> Option<usize> {
+    ask(&format!(
+        "{} {} [1-{}] ",
+        colorize("[?]", colors::YELLOW),
+        question,
+        count
+    ));
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    match answer.trim() {
+        "" => Some(0),
+        n => n
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=count).contains(n))
+            .map(|n| n - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory for one test's log files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jose-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path, suffix: &str) -> Option<String> {
+        let mut name = path.to_path_buf().into_os_string();
+        name.push(suffix);
+        fs::read_to_string(name).ok()
+    }
+
+    #[test]
+    fn rotates_when_full() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("jose.log");
+        let mut sink = FileSink::open(&path, 10, 2).unwrap();
+
+        sink.write("first line\n").unwrap();
+        assert_eq!(read(&path, ".1"), None);
+        // The file reached `max_bytes`, so the next write starts a new one.
+        sink.write("second\n").unwrap();
+        assert_eq!(read(&path, "").as_deref(), Some("second\n"));
+        assert_eq!(read(&path, ".1").as_deref(), Some("first line\n"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn shifts_and_drops_the_oldest() {
+        let dir = temp_dir("shift");
+        let path = dir.join("jose.log");
+        let mut sink = FileSink::open(&path, 1, 2).unwrap();
+
+        for line in ["a\n", "b\n", "c\n", "d\n"] {
+            sink.write(line).unwrap();
+        }
+        assert_eq!(read(&path, "").as_deref(), Some("d\n"));
+        assert_eq!(read(&path, ".1").as_deref(), Some("c\n"));
+        assert_eq!(read(&path, ".2").as_deref(), Some("b\n"));
+        // `a` was shifted past `keep` and dropped.
+        assert_eq!(read(&path, ".3"), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keep_zero_truncates() {
+        let dir = temp_dir("truncate");
+        let path = dir.join("jose.log");
+        let mut sink = FileSink::open(&path, 4, 0).unwrap();
+
+        sink.write("old entry\n").unwrap();
+        sink.write("new\n").unwrap();
+        assert_eq!(read(&path, "").as_deref(), Some("new\n"));
+        assert_eq!(read(&path, ".1"), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reopening_counts_existing_size() {
+        let dir = temp_dir("reopen");
+        let path = dir.join("jose.log");
+        fs::write(&path, "0123456789\n").unwrap();
+
+        let mut sink = FileSink::open(&path, 10, 1).unwrap();
+        sink.write("next\n").unwrap();
+        assert_eq!(read(&path, "").as_deref(), Some("next\n"));
+        assert_eq!(read(&path, ".1").as_deref(), Some("0123456789\n"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,21 +1,30 @@
+mod attachment;
 mod auth;
 mod clipboard;
 mod config;
+mod history;
+mod http;
 mod jwt;
 mod log;
 mod oauth;
+mod paths;
 mod prompt;
 mod provider;
 mod shell;
+mod sse;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
+use crate::attachment::ImageAttachment;
 use crate::auth::AuthData;
 use crate::clipboard::copy_to_clipboard;
-use crate::config::{Config, ProviderKind, AVAILABLE_MODELS};
-use crate::jwt::parse_jwt_claims;
+use crate::config::{known_model_names, Config, ModelInfo, ProviderKind, KNOWN_MODELS};
+use crate::history::HistoryEntry;
 use crate::oauth::do_login;
+use crate::shell::ShellType;
 
 #[derive(Parser)]
 #[command(name = "jose")]
@@ -32,12 +41,75 @@ struct Cli {
     /// Model to use (e.g., gpt-5, gpt-5-codex)
     #[arg(short, long)]
     model: Option<String>,
+
+    /// Skip confirmation prompts (e.g. for models listed in `confirm_models`)
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Attach an image (PNG, JPEG, GIF, WebP) for vision-capable models
+    #[arg(long)]
+    image: Option<PathBuf>,
+
+    /// Offer to run the generated command after printing it
+    #[arg(long)]
+    run: bool,
+
+    /// Don't copy the command to the clipboard (also: `JOSE_NO_CLIPBOARD`)
+    #[arg(long)]
+    no_copy: bool,
+
+    /// Sampling seed for reproducible output (best-effort, model-dependent)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Print the result as a JSON object instead of log lines
+    #[arg(long, conflicts_with = "run")]
+    json: bool,
+
+    /// Pick which of the suggested commands to copy (and run), on a terminal
+    #[arg(long, conflicts_with = "json")]
+    select: bool,
+
+    /// Have the model check the command for destructive effects before it is
+    /// copied, even with `safety_check` off in the config
+    #[arg(long)]
+    danger_check: bool,
+
+    /// Shell to target instead of the detected one (bash, zsh, fish, pwsh, ...)
+    #[arg(long, global = true)]
+    shell: Option<ShellType>,
+
+    /// Only print errors (and the bare command)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print diagnostics: endpoints, models, token expiry
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Wrap printed prose to N columns (`0` or `auto` = no wrapping)
+    #[arg(long, global = true, value_name = "N", value_parser = parse_wrap_width)]
+    wrap_width: Option<usize>,
+}
+
+fn parse_wrap_width(value: &str) -> Result<usize, String> {
+    if value == "auto" {
+        return Ok(0);
+    }
+    value
+        .parse()
+        .map_err(|_| format!("expected a column count or `auto`, got `{}`", value))
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Authenticate with ChatGPT
-    Login,
+    Login {
+        /// No local browser or callback server: print the login URL and
+        /// paste the redirect URL back (for SSH sessions)
+        #[arg(long)]
+        manual: bool,
+    },
     /// Show authentication status
     Info,
     /// Show the current model and available models, or set a new one
@@ -45,11 +117,67 @@ enum Commands {
         #[command(subcommand)]
         command: Option<ModelCommands>,
     },
+    /// List known model names for `--model` / `model set`
+    Models,
     /// Show the current provider, or switch/configure one
     Provider {
         #[command(subcommand)]
         command: Option<ProviderCommands>,
     },
+    /// Read or change settings in the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Explain how two commands differ and which is safer/faster
+    #[command(name = "diff-explain")]
+    DiffExplain {
+        /// First command (quote it)
+        a: String,
+        /// Second command (quote it)
+        b: String,
+    },
+    /// Explain what a command does, flag by flag, and what could go wrong
+    Explain {
+        /// The command to explain (quote it, or pass it after `--`)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Summarize piped logs or command output (e.g. `journalctl -u foo | jose summarize`)
+    Summarize {
+        /// File to summarize (reads stdin if omitted or `-`)
+        file: Option<PathBuf>,
+        /// Answer as a bullet list
+        #[arg(long)]
+        bullets: bool,
+        /// How detailed the summary should be
+        #[arg(long, value_enum, default_value_t = SummaryLength::Short)]
+        length: SummaryLength,
+    },
+    /// Show recent queries and the commands they produced
+    History {
+        /// Number of entries to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Delete the stored history
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Send a raw JSON payload to the ChatGPT Responses API (advanced, unstable)
+    #[command(name = "raw-request")]
+    RawRequest {
+        /// File containing the JSON payload (reads stdin if omitted or `-`)
+        file: Option<PathBuf>,
+        /// Acknowledge that this is an unstable debugging interface
+        #[arg(long)]
+        unstable: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SummaryLength {
+    Short,
+    Long,
 }
 
 #[derive(Subcommand)]
@@ -59,6 +187,34 @@ enum ModelCommands {
         /// The model name to set as default
         model: String,
     },
+    /// Show context window and capabilities of a model
+    Info {
+        /// Model to describe (defaults to the current model)
+        model: Option<String>,
+        /// Print the metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print a setting's value
+    Get {
+        /// Setting name, e.g. `request_timeout_secs`
+        key: String,
+    },
+    /// Change a setting (JSON values like `true`, `30`, `null`, or plain text)
+    Set {
+        /// Setting name, e.g. `shell`
+        key: String,
+        /// New value
+        value: String,
+    },
+    /// Print the config file's location
+    Path,
+    /// Open the config file in $VISUAL / $EDITOR
+    Edit,
 }
 
 #[derive(Subcommand)]
@@ -87,34 +243,87 @@ enum ProviderSet {
 }
 
 fn cmd_info() -> Result<()> {
-    match AuthData::load()? {
-        Some(auth) => {
-            if let Some(claims) = parse_jwt_claims(&auth.tokens.access_token) {
-                if let Some(exp) = claims.get("exp").and_then(|v| v.as_i64()) {
-                    let expiry = chrono::DateTime::from_timestamp(exp, 0)
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
-                        .unwrap_or_else(|| "unknown".to_string());
-                    log::success(&format!("Authenticated. Token expires: {}", expiry));
-                } else {
-                    log::success("Authenticated.");
-                }
-            } else {
-                log::warn("Auth file exists but token could not be parsed.");
+    let config = Config::load()?;
+    let Some(mut auth) = AuthData::load()? else {
+        log::error("Not authenticated. Run `jose login`");
+        return Ok(());
+    };
+    let mut status = auth.describe();
+    if !status.authenticated {
+        log::warn("Auth file exists but token could not be parsed.");
+        return Ok(());
+    }
+
+    // An expired session is only a problem if the refresh token is dead too.
+    if status.needs_refresh {
+        log::info("Token expired or expiring soon, refreshing...");
+        match auth::get_valid_tokens(&config) {
+            Ok(Some(tokens)) => {
+                auth.tokens = tokens;
+                status = auth.describe();
             }
+            Ok(None) => {}
+            Err(e) => log::warn(&format!("Refresh failed: {}. Run `jose login`", e)),
         }
-        None => {
-            log::error("Not authenticated. Run `jose login`");
+    }
+
+    match status.expires_at {
+        Some(exp) => {
+            let expiry = exp.format("%Y-%m-%d %H:%M:%S UTC");
+            let remaining = exp.signed_duration_since(chrono::Utc::now());
+            let verb = if remaining < chrono::TimeDelta::zero() {
+                "expired"
+            } else {
+                "expires"
+            };
+            log::success(&format!(
+                "Authenticated. Token {}: {} ({})",
+                verb,
+                expiry,
+                relative_time(remaining)
+            ));
+        }
+        None => log::success("Authenticated."),
+    }
+    if let Some(email) = &status.email {
+        log::info(&format!("Email: {}", email));
+    }
+    if let Some(account_id) = &status.account_id {
+        log::info(&format!("Account: {}", account_id));
+    }
+
+    if config.verify_jwt {
+        match auth.verify(&config) {
+            Ok(()) => log::success("Token signature verified against the issuer's JWKS."),
+            Err(e) => log::warn(&format!("Token failed verification: {}", e)),
         }
     }
     Ok(())
 }
 
+/// `in 42 minutes` / `3 hours ago`, in the largest whole unit.
+fn relative_time(delta: chrono::TimeDelta) -> String {
+    let secs = delta.num_seconds().abs();
+    let (n, unit) = match secs {
+        0..60 => (secs, "second"),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    let plural = if n == 1 { "" } else { "s" };
+    if delta.num_seconds() < 0 {
+        format!("{} {}{} ago", n, unit, plural)
+    } else {
+        format!("in {} {}{}", n, unit, plural)
+    }
+}
+
 fn cmd_model_show() -> Result<()> {
     let config = Config::load()?;
     log::success(&format!("Current model: {}", config.default_model));
     log::info("Available models:");
-    for model in AVAILABLE_MODELS {
-        if *model == config.default_model {
+    for model in known_model_names() {
+        if model == config.default_model {
             log::command(&format!("{} (current)", model));
         } else {
             log::command(model);
@@ -123,11 +332,29 @@ fn cmd_model_show() -> Result<()> {
     Ok(())
 }
 
+fn cmd_models() -> Result<()> {
+    let config = Config::load()?;
+    let width = KNOWN_MODELS.iter().map(|m| m.name.len()).max().unwrap_or(0);
+    log::info("Known models:");
+    for model in KNOWN_MODELS {
+        let current = model.name == config.default_model;
+        let marker = if current { "→" } else { " " };
+        log::command(&format!(
+            "{} {:<width$}  {}",
+            marker, model.name, model.recommended_for
+        ));
+    }
+    if !KNOWN_MODELS.iter().any(|m| m.name == config.default_model) {
+        log::info(&format!("Current model: {} (custom)", config.default_model));
+    }
+    Ok(())
+}
+
 fn cmd_model_set(model: &str) -> Result<()> {
     let mut config = Config::load()?;
     // The known-model list only applies to the ChatGPT backend; openai-compatible
     // servers expose arbitrary model names.
-    if config.provider == ProviderKind::Chatgpt && !AVAILABLE_MODELS.contains(&model) {
+    if config.provider == ProviderKind::Chatgpt && !known_model_names().any(|m| m == model) {
         log::warn(&format!(
             "`{}` is not in the known model list. Setting it anyway.",
             model
@@ -139,6 +366,102 @@ fn cmd_model_set(model: &str) -> Result<()> {
     Ok(())
 }
 
+fn cmd_model_info(model: Option<&str>, json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let model = model.unwrap_or(&config.default_model);
+    let mut info = config.model_info(model);
+
+    // Openai-compatible servers may report metadata the static table lacks.
+    match provider::fetch_model_entry(&config, model) {
+        Ok(Some(entry)) => {
+            let info = info.get_or_insert_with(|| ModelInfo {
+                name: model.to_string(),
+                ..Default::default()
+            });
+            if info.context_window.is_none() {
+                info.context_window = ["context_window", "context_length", "max_model_len"]
+                    .iter()
+                    .find_map(|key| entry[*key].as_u64());
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn(&format!("Could not query the models endpoint: {}", e)),
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    let Some(info) = info else {
+        log::warn(&format!("No metadata known for `{}`.", model));
+        log::info("Add it under `model_info` in the config file.");
+        return Ok(());
+    };
+
+    let flag = |v: Option<bool>| match v {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    };
+    log::success(&format!("Model: {}", info.name));
+    log::info(&format!(
+        "Context window: {}",
+        info.context_window
+            .map(|n| format!("{} tokens", n))
+            .unwrap_or_else(|| "unknown".to_string())
+    ));
+    log::info(&format!("Streaming: {}", flag(info.streaming)));
+    log::info(&format!("Tools: {}", flag(info.tools)));
+    log::info(&format!("Reasoning: {}", flag(info.reasoning)));
+    log::info(&format!("Vision: {}", flag(info.vision)));
+    if let Some(rec) = &info.recommended_for {
+        log::info(&format!("Recommended for: {}", rec));
+    }
+    Ok(())
+}
+
+fn cmd_config(command: ConfigCommands) -> Result<()> {
+    match command {
+        ConfigCommands::Get { key } => {
+            // Strings print bare so `$(jose config get shell)` works.
+            match Config::load_unvalidated()?.get(&key)? {
+                serde_json::Value::String(value) => println!("{}", value),
+                value => println!("{}", value),
+            }
+        }
+        ConfigCommands::Set { key, value } => {
+            let mut config = Config::load_unvalidated()?;
+            config.set(&key, &value)?;
+            config.save()?;
+            log::success(&format!("{} set to: {}", key, config.get(&key)?));
+        }
+        ConfigCommands::Path => println!("{}", Config::config_path()?.display()),
+        ConfigCommands::Edit => {
+            let path = Config::config_path()?;
+            if !path.exists() {
+                Config::default().save()?;
+            }
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+            // $EDITOR may carry arguments (`code --wait`), so let the shell split it.
+            let line = format!("{} \"{}\"", editor, path.display());
+            let status = shell::shell_command(shell::detect_shell(), &line)
+                .status()
+                .with_context(|| format!("Failed to start {}", editor))?;
+            if !status.success() {
+                anyhow::bail!("{} exited with {}", editor, status);
+            }
+            match Config::load() {
+                Ok(_) => log::success("Config saved."),
+                Err(e) => log::error(&format!("The config no longer loads: {}", e)),
+            }
+        }
+    }
+    Ok(())
+}
+
 fn cmd_provider_show() -> Result<()> {
     let config = Config::load()?;
     log::success(&format!("Current provider: {}", config.provider.as_str()));
@@ -175,10 +498,316 @@ fn cmd_provider_set(set: &ProviderSet) -> Result<()> {
     Ok(())
 }
 
-fn cmd_query(prompt: &str, model: Option<&str>) -> Result<()> {
+fn cmd_diff_explain(a: &str, b: &str, model: Option<&str>) -> Result<()> {
     let config = Config::load()?;
     let model = model.unwrap_or(&config.default_model);
 
+    log::info(&format!("Comparing commands ({})...", model));
+    let diff = provider::explain_diff(&config, a, b, model)?;
+
+    log::success(&diff.summary);
+    if !diff.differences.is_empty() {
+        log::info("Differences:");
+        for difference in &diff.differences {
+            log::bullet(difference);
+        }
+    }
+
+    let describe = |verdict: &Option<String>| match verdict.as_deref() {
+        Some("A") => format!("A ({})", a),
+        Some("B") => format!("B ({})", b),
+        Some(other) => other.to_string(),
+        None => "unknown".to_string(),
+    };
+    if diff.safer.is_some() || diff.faster.is_some() {
+        log::info(&format!("Safer: {}", describe(&diff.safer)));
+        log::info(&format!("Faster: {}", describe(&diff.faster)));
+    }
+    if let Some(rec) = &diff.recommendation {
+        log::info(&format!("Recommendation: {}", rec));
+    }
+    Ok(())
+}
+
+fn cmd_explain(command: &str, model: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let model = model.unwrap_or(&config.default_model);
+
+    log::info(&format!("Explaining command ({})...", model));
+    let instructions = prompt::build_explain_prompt();
+    let explanation =
+        provider::complete(&config, instructions, command, model, &Default::default())?;
+    log::prose(&explanation);
+    Ok(())
+}
+
+/// Read `file`, or all of stdin when no file (or `-`) is given.
+fn read_file_or_stdin(file: Option<&Path>) -> Result<String> {
+    match file {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display())),
+        _ => std::io::read_to_string(std::io::stdin()).context("Failed to read stdin"),
+    }
+}
+
+/// Most input characters sent to the model; beyond this only the tail is kept.
+const MAX_INPUT_CHARS: usize = 100_000;
+
+/// Keep the last [`MAX_INPUT_CHARS`] of `input` (for logs the end matters
+/// most), marking the cut so the model knows the start is missing.
+fn truncate_input(input: &str) -> String {
+    let total = input.chars().count();
+    if total <= MAX_INPUT_CHARS {
+        return input.to_string();
+    }
+    let dropped = total - MAX_INPUT_CHARS;
+    log::warn(&format!(
+        "Input is {} characters; only the last {} are sent.",
+        total, MAX_INPUT_CHARS
+    ));
+    let tail: String = input.chars().skip(dropped).collect();
+    format!(
+        "[... {} earlier characters truncated ...]\n{}",
+        dropped, tail
+    )
+}
+
+fn cmd_summarize(
+    file: Option<&Path>,
+    bullets: bool,
+    length: SummaryLength,
+    model: Option<&str>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let model = model.unwrap_or(&config.default_model);
+
+    if file.is_none() && std::io::stdin().is_terminal() {
+        anyhow::bail!("Nothing to summarize. Pipe input in or pass a file.");
+    }
+    let input = read_file_or_stdin(file)?;
+    if input.trim().is_empty() {
+        anyhow::bail!("Nothing to summarize: input is empty.");
+    }
+    let input = truncate_input(&input);
+
+    let instructions = prompt::build_summary_prompt(bullets, length == SummaryLength::Long);
+    let summary = provider::complete(&config, instructions, &input, model, &Default::default())?;
+    log::prose(&summary);
+    Ok(())
+}
+
+fn cmd_history(limit: usize, clear: bool) -> Result<()> {
+    if clear {
+        history::clear()?;
+        log::success("History cleared.");
+        return Ok(());
+    }
+
+    let entries = history::load(limit)?;
+    if entries.is_empty() {
+        log::info("No history yet.");
+        return Ok(());
+    }
+    for entry in entries {
+        let when = chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|dt| {
+                dt.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or(entry.timestamp);
+        log::info(&format!("{} [{}] {}", when, entry.model, entry.prompt));
+        for command in &entry.commands {
+            log::command(command);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_raw_request(file: Option<&Path>, unstable: bool) -> Result<()> {
+    if !unstable {
+        anyhow::bail!(
+            "`raw-request` is an advanced, unstable interface. Pass --unstable to use it."
+        );
+    }
+
+    let content = read_file_or_stdin(file)?;
+    let payload: serde_json::Value =
+        serde_json::from_str(&content).context("Payload is not valid JSON")?;
+
+    let config = Config::load()?;
+    log::warn("Sending raw payload; the response is printed unparsed.");
+    provider::raw_request(&config, &payload, &mut std::io::stdout())
+}
+
+/// Flags that shape a single `cmd_query` invocation.
+struct QueryOptions<'a> {
+    model: Option<&'a str>,
+    yes: bool,
+    image: Option<&'a Path>,
+    run: bool,
+    json: bool,
+    select: bool,
+    danger_check: bool,
+    copy: bool,
+    seed: Option<u64>,
+}
+
+/// Guard against accidentally spending queries on a model listed in
+/// `confirm_models`: ask once on a TTY, otherwise require `--yes`.
+fn confirm_model(config: &Config, model: &str, yes: bool) -> Result<()> {
+    if yes || !config.confirm_models.iter().any(|m| m == model) {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Model `{}` requires confirmation. Pass --yes to use it non-interactively.",
+            model
+        );
+    }
+    if !log::confirm(&format!("`{}` is marked as expensive. Continue?", model)) {
+        anyhow::bail!("Aborted.");
+    }
+    Ok(())
+}
+
+/// Whether `command` may be copied or run: commands touching one of the
+/// configured `protected_paths` need a confirmation (or `--yes`).
+fn confirm_protected(config: &Config, command: &str, yes: bool) -> bool {
+    let Some(path) = shell::protected_path(command, &config.protected_paths) else {
+        return true;
+    };
+    let warning = format!("This command touches protected path {}.", path);
+    confirm_risky(&warning, yes)
+}
+
+/// Print `warning`, then decide whether to copy/run anyway: yes with
+/// `--yes`, no without a TTY to ask on, otherwise whatever the user answers.
+fn confirm_risky(warning: &str, yes: bool) -> bool {
+    log::warn(warning);
+    if yes {
+        return true;
+    }
+    if !std::io::stdin().is_terminal() {
+        log::warn("Not copying or running it (pass --yes to allow).");
+        return false;
+    }
+    log::confirm("Copy/run it anyway?")
+}
+
+/// Second opinion on destructive commands, for interactive use: ask the model,
+/// and if it flags `command`, warn and confirm before copying or running it.
+fn confirm_safe(config: &Config, command: &str, model: &str, opts: &QueryOptions) -> bool {
+    let interactive = std::io::stdout().is_terminal() && !opts.json;
+    if !(config.safety_check || opts.danger_check) || !interactive || command.starts_with('#') {
+        return true;
+    }
+    log::debug(&format!("Safety check ({})", model));
+    let verdict = match provider::check_danger(config, command, model) {
+        Ok(verdict) => verdict,
+        Err(e) => {
+            log::warn(&format!("Safety check failed: {}", e));
+            return true;
+        }
+    };
+    if !verdict.destructive {
+        return true;
+    }
+    let warning = match verdict.reason.filter(|r| !r.trim().is_empty()) {
+        Some(reason) => format!("This command looks destructive: {}", reason),
+        None => "This command looks destructive.".to_string(),
+    };
+    confirm_risky(&warning, opts.yes)
+}
+
+/// Run `command` through `shell` after confirming, then exit with its status.
+///
+/// Without a TTY on stdin nothing runs unless `--yes` was passed, so piping
+/// into `jose --run` can never execute a command unseen.
+fn run_command(command: &str, shell: ShellType, yes: bool) -> Result<()> {
+    if command.starts_with('#') {
+        log::warn("Nothing to run: the model did not return a command.");
+        return Ok(());
+    }
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            log::warn("Not running: stdin is not a terminal (pass --yes to run anyway).");
+            return Ok(());
+        }
+        if !log::confirm("Run this?") {
+            return Ok(());
+        }
+    }
+
+    let status = shell::shell_command(shell, command)
+        .status()
+        .with_context(|| format!("Failed to start {}", shell.name()))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Apply the configured clean-ups to one command of model output.
+fn clean_command<'a>(config: &Config, shell: ShellType, command: &'a str) -> &'a str {
+    if config.strip_prompt_prefix {
+        shell::strip_prompt_prefix(command, shell)
+    } else {
+        command
+    }
+}
+
+/// Openers models use for filler like "Sure! Here's the command:".
+const ACKNOWLEDGEMENTS: &[&str] = &[
+    "sure",
+    "certainly",
+    "of course",
+    "absolutely",
+    "okay",
+    "ok",
+    "great",
+    "here's",
+    "here is",
+];
+
+/// Drop a leading acknowledgement line, e.g. "Sure! Here's the command:".
+///
+/// Deliberately conservative: the whole first line must open with a known
+/// filler word and end in `.`, `!` or `:`, and something must follow it. A
+/// command sharing the line ("Sure: ls -la") is left untouched.
+fn strip_acknowledgement(text: &str) -> &str {
+    let Some((first_line, rest)) = text.split_once('\n') else {
+        return text;
+    };
+    let first_line = first_line.trim();
+    let lower = first_line.to_lowercase();
+    let is_filler = ACKNOWLEDGEMENTS.iter().any(|ack| {
+        lower.starts_with(ack)
+            && lower[ack.len()..]
+                .chars()
+                .next()
+                .is_none_or(|c| !c.is_alphanumeric())
+    });
+    let rest = rest.trim_start();
+    if is_filler && first_line.ends_with(['.', '!', ':']) && !rest.is_empty() {
+        rest
+    } else {
+        text
+    }
+}
+
+fn cmd_query(prompt: &str, opts: &QueryOptions) -> Result<()> {
+    let config = Config::load()?;
+    let model = opts.model.unwrap_or(&config.default_model);
+
+    confirm_model(&config, model, opts.yes)?;
+
+    let info = config.model_info(model);
+    let image = opts.image.map(ImageAttachment::load).transpose()?;
+    if image.is_some() && info.as_ref().and_then(|i| i.vision) != Some(true) {
+        log::warn(&format!(
+            "`{}` is not known to accept images; the request may fail.",
+            model
+        ));
+    }
+
     match config.provider {
         ProviderKind::Chatgpt => log::info(&format!("Querying chatgpt ({})...", model)),
         ProviderKind::OpenAiCompatible => {
@@ -187,50 +816,211 @@ fn cmd_query(prompt: &str, model: Option<&str>) -> Result<()> {
         }
     }
 
-    let result = provider::generate(&config, prompt, model)?;
+    let shell = shell::detect_shell();
+
+    // A seed is paired with temperature 0, which reasoning models reject.
+    let reasoning = info.and_then(|i| i.reasoning) == Some(true);
+    let request = provider::RequestOptions {
+        image: image.as_ref(),
+        seed: opts.seed,
+        temperature: opts.seed.filter(|_| !reasoning).map(|_| 0.0),
+        ..Default::default()
+    };
 
-    if result.is_empty() {
+    let mut used_model = model;
+    let commands = loop {
+        // Show the answer as it streams in, then replace it with the cleaned-up
+        // commands. `--json` keeps the buffered path so stdout stays parseable.
+        // Until the first delta arrives (or for the whole request with
+        // non-streaming providers) a spinner shows it's not hung.
+        let mut preview = log::Preview::new();
+        let mut spinner = log::Spinner::start("Waiting for the model...");
+        let generated = if opts.json {
+            provider::generate(&config, prompt, model, &request)
+        } else {
+            let on_delta = &mut |delta: &str| {
+                spinner.stop();
+                preview.write(delta);
+            };
+            provider::generate_streaming(&config, prompt, model, &request, on_delta)
+        };
+        spinner.stop();
+        preview.clear();
+        let result = match generated {
+            Ok(result) => result,
+            Err(e) => match config.fallback_model.as_deref() {
+                Some(fallback) if fallback != model && provider::should_fall_back(&e) => {
+                    log::warn(&format!("{} failed: {}", model, e));
+                    log::info(&format!("Retrying with fallback model {}...", fallback));
+                    used_model = fallback;
+                    provider::generate(&config, prompt, fallback, &request)?
+                }
+                _ => return Err(e),
+            },
+        };
+
+        let result = if config.trim_acknowledgements {
+            strip_acknowledgement(&result).to_string()
+        } else {
+            result
+        };
+
+        if result.is_empty() {
+            anyhow::bail!("Empty response from provider");
+        }
+
+        // One command per line, except blocks that span lines (heredocs,
+        // loops, `\` continuations) which stay whole.
+        let commands: Vec<String> = shell::split_commands(&result, shell)
+            .iter()
+            .map(|c| clean_command(&config, shell, c).to_string())
+            .collect();
+
+        // A malformed command usually means the stream was cut short.
+        let first = commands.first().map(String::as_str).unwrap_or_default();
+        let issues = if first.starts_with('#') {
+            Vec::new()
+        } else {
+            shell::lint_command(first, shell)
+        };
+        if issues.is_empty() {
+            break commands;
+        }
+        for issue in issues {
+            log::warn(&format!("Generated command looks malformed: {}", issue));
+        }
+        if !opts.json && std::io::stdin().is_terminal() && log::confirm("Regenerate?") {
+            log::info("Regenerating...");
+            continue;
+        }
+        break commands;
+    };
+
+    if let Err(e) = history::append(&HistoryEntry::new(used_model, prompt, &commands)) {
+        log::warn(&format!("Failed to save history: {}", e));
+    }
+
+    // First command is the main one; the rest are alternatives
+    let Some((mut command, mut alternatives)) = commands.split_first() else {
         anyhow::bail!("Empty response from provider");
+    };
+
+    // `--select` lets the user pick one instead; scripts keep the first.
+    let interactive = std::io::stdout().is_terminal() && std::io::stdin().is_terminal();
+    if opts.select && interactive && !alternatives.is_empty() {
+        log::info("Suggested commands:");
+        for (i, cmd) in commands.iter().enumerate() {
+            log::numbered(i + 1, cmd);
+        }
+        let Some(choice) = log::choose("Which one?", commands.len()) else {
+            anyhow::bail!("No command selected");
+        };
+        command = &commands[choice];
+        alternatives = &[];
     }
 
-    // Get first line as main command
-    let lines: Vec<&str> = result.lines().collect();
-    let command = lines.first().unwrap_or(&"");
+    // Nothing to guard when the command is only printed (and the safety
+    // review would cost a second request).
+    let allowed = !(opts.copy || opts.run)
+        || (confirm_protected(&config, command, opts.yes)
+            && confirm_safe(&config, command, used_model, opts));
 
     // Copy to clipboard
-    if let Err(e) = copy_to_clipboard(command) {
-        log::warn(&format!("Failed to copy to clipboard: {}", e));
+    let copied = if opts.copy && allowed {
+        match copy_to_clipboard(command, config.clipboard_target) {
+            Ok(()) => {
+                log::success("Command copied to clipboard:");
+                true
+            }
+            Err(e) => {
+                log::warn(&format!("Failed to copy to clipboard: {}", e));
+                false
+            }
+        }
     } else {
-        log::success("Command copied to clipboard:");
+        log::success("Command:");
+        false
+    };
+
+    if opts.json {
+        let output = serde_json::json!({
+            "command": command,
+            "alternatives": alternatives,
+            "model": used_model,
+            "copied": copied,
+        });
+        println!("{}", output);
+        return Ok(());
     }
 
-    log::command(command);
+    // `-q` prints just the command, bare, for `$(jose -q ...)`
+    if log::level() == log::Level::Quiet {
+        println!("{}", command);
+    } else {
+        log::command(command);
+    }
 
     // Show alternatives if any
-    if lines.len() > 1 {
-        let alternatives: Vec<&str> = lines[1..]
-            .iter()
-            .filter(|l| !l.trim().is_empty())
-            .copied()
-            .collect();
-
-        if !alternatives.is_empty() {
-            log::info("Alternatives:");
-            for alt in alternatives {
-                log::command(alt);
-            }
+    if !alternatives.is_empty() && log::level() > log::Level::Quiet {
+        log::info("Alternatives:");
+        for alt in alternatives {
+            log::command(alt);
         }
     }
 
+    if opts.run && allowed {
+        run_command(command, shell, opts.yes)?;
+    }
+
     Ok(())
 }
 
 fn main() -> Result<()> {
+    log::init_terminal();
     let cli = Cli::parse();
+    if cli.quiet {
+        log::set_level(log::Level::Quiet);
+    } else if cli.verbose {
+        log::set_level(log::Level::Verbose);
+    }
+
+    let config = Config::load().ok();
+    let wrap_width = match cli.wrap_width {
+        Some(width) => width,
+        None => config.as_ref().map(|c| c.wrap_width).unwrap_or(0),
+    };
+    log::set_wrap_width(wrap_width);
+    // Shell precedence: --shell, then the config, then detection. `jose
+    // config` skips the config's shell so a bad value can still be fixed.
+    let editing_config = matches!(cli.command, Some(Commands::Config { .. }));
+    let config_shell = config
+        .as_ref()
+        .and_then(|c| c.shell.as_deref())
+        .filter(|_| !editing_config);
+    let shell = match (cli.shell, config_shell) {
+        (Some(shell), _) => Some(shell),
+        (None, Some(name)) => Some(
+            name.parse::<ShellType>()
+                .map_err(|e| anyhow::anyhow!("Invalid `shell` in config: {}", e))?,
+        ),
+        (None, None) => None,
+    };
+    if let Some(shell) = shell {
+        shell::set_shell_override(shell);
+    }
+    if let Some(config) = &config {
+        if let Some(path) = &config.log_file {
+            if let Err(e) = log::set_log_file(path, config.log_max_bytes, config.log_keep) {
+                let path = path.display();
+                log::warn(&format!("Failed to open log file {}: {}", path, e));
+            }
+        }
+    }
+    log::set_quiet(cli.json);
 
     match cli.command {
-        Some(Commands::Login) => {
-            if do_login()? {
+        Some(Commands::Login { manual }) => {
+            if do_login(manual)? {
                 std::process::exit(0);
             } else {
                 std::process::exit(1);
@@ -242,22 +1032,103 @@ fn main() -> Result<()> {
         Some(Commands::Model { command }) => match command {
             None => cmd_model_show()?,
             Some(ModelCommands::Set { model }) => cmd_model_set(&model)?,
+            Some(ModelCommands::Info { model, json }) => cmd_model_info(model.as_deref(), json)?,
         },
+        Some(Commands::Models) => {
+            cmd_models()?;
+        }
         Some(Commands::Provider { command }) => match command {
             None => cmd_provider_show()?,
             Some(ProviderCommands::Set { kind }) => cmd_provider_set(&kind)?,
         },
+        Some(Commands::Config { command }) => {
+            cmd_config(command)?;
+        }
+        Some(Commands::DiffExplain { a, b }) => {
+            cmd_diff_explain(&a, &b, cli.model.as_deref())?;
+        }
+        Some(Commands::Explain { command }) => {
+            cmd_explain(&command.join(" "), cli.model.as_deref())?;
+        }
+        Some(Commands::Summarize {
+            file,
+            bullets,
+            length,
+        }) => {
+            cmd_summarize(file.as_deref(), bullets, length, cli.model.as_deref())?;
+        }
+        Some(Commands::History { limit, clear }) => {
+            cmd_history(limit, clear)?;
+        }
+        Some(Commands::RawRequest { file, unstable }) => {
+            cmd_raw_request(file.as_deref(), unstable)?;
+        }
         None => {
-            if cli.prompt.is_empty() {
+            // With no args, a piped stdin is the prompt: `echo "..." | jose`.
+            let prompt = if !cli.prompt.is_empty() {
+                cli.prompt.join(" ")
+            } else if !std::io::stdin().is_terminal() {
+                read_file_or_stdin(None)?.trim().to_string()
+            } else {
+                String::new()
+            };
+            if prompt.is_empty() {
                 log::error("Please provide a prompt or use a subcommand.");
                 log::info("Run `jose --help` for usage.");
                 std::process::exit(1);
             }
 
-            let prompt = cli.prompt.join(" ");
-            cmd_query(&prompt, cli.model.as_deref())?;
+            let opts = QueryOptions {
+                model: cli.model.as_deref(),
+                yes: cli.yes,
+                image: cli.image.as_deref(),
+                run: cli.run,
+                json: cli.json,
+                select: cli.select,
+                danger_check: cli.danger_check,
+                copy: !cli.no_copy && std::env::var_os("JOSE_NO_CLIPBOARD").is_none(),
+                seed: cli.seed,
+            };
+            cmd_query(&prompt, &opts)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_acknowledgement_line() {
+        assert_eq!(
+            strip_acknowledgement("Sure! Here's the command:\nls -la\nfind . -type f"),
+            "ls -la\nfind . -type f"
+        );
+        assert_eq!(strip_acknowledgement("Of course.\n\ndu -sh ."), "du -sh .");
+        assert_eq!(strip_acknowledgement("OK:\npwd"), "pwd");
+    }
+
+    #[test]
+    fn relative_time_has_no_verb() {
+        let minutes = chrono::TimeDelta::minutes;
+        assert_eq!(relative_time(minutes(42)), "in 42 minutes");
+        assert_eq!(relative_time(minutes(-180)), "3 hours ago");
+        assert_eq!(relative_time(chrono::TimeDelta::days(1)), "in 1 day");
+    }
+
+    #[test]
+    fn keeps_clean_responses() {
+        for text in [
+            "ls -la\nfind . -type f",
+            "okular report.pdf\nevince report.pdf",
+            "Sure: ls -la",
+            "Sure!",
+            "Here is what `tar -x` does\nIt extracts an archive.",
+            "# Cannot be done without root.",
+        ] {
+            assert_eq!(strip_acknowledgement(text), text);
+        }
+    }
+}
@@ -3,13 +3,16 @@ use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::distr::{Alphanumeric, SampleString};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
 use std::net::TcpListener;
+use std::time::{Duration, Instant};
 
 use crate::auth::{AuthData, Tokens};
-use crate::config::{CLIENT_ID, OAUTH_ISSUER, OAUTH_PORT, OAUTH_TOKEN_URL};
-use crate::jwt::parse_jwt_claims;
+use crate::config::{Config, CLIENT_ID, OAUTH_PORT};
+use crate::http;
+use crate::jwt::parse_claims;
 use crate::log;
+use crate::provider;
 
 #[derive(Debug, Clone)]
 pub struct PkceCodes {
@@ -34,7 +37,7 @@ fn redirect_uri() -> String {
     format!("http://localhost:{}/auth/callback", OAUTH_PORT)
 }
 
-pub fn build_auth_url(pkce: &PkceCodes, state: &str) -> String {
+pub fn build_auth_url(config: &Config, pkce: &PkceCodes, state: &str) -> String {
     let redirect_uri = redirect_uri();
 
     let params = [
@@ -55,12 +58,13 @@ pub fn build_auth_url(pkce: &PkceCodes, state: &str) -> String {
         .collect::<Vec<_>>()
         .join("&");
 
-    format!("{}/oauth/authorize?{}", OAUTH_ISSUER, query)
+    format!("{}/oauth/authorize?{}", config.oauth_issuer(), query)
 }
 
 fn exchange_code(code: &str, pkce: &PkceCodes) -> Result<Tokens> {
+    let config = Config::load()?;
     let redirect_uri = redirect_uri();
-    let client = reqwest::blocking::Client::new();
+    let client = http::client()?;
 
     let body = format!(
         "grant_type=authorization_code&code={}&redirect_uri={}&client_id={}&code_verifier={}",
@@ -71,11 +75,12 @@ fn exchange_code(code: &str, pkce: &PkceCodes) -> Result<Tokens> {
     );
 
     let resp = client
-        .post(OAUTH_TOKEN_URL)
+        .post(config.oauth_token_url())
         .header("Content-Type", "application/x-www-form-urlencoded")
         .body(body)
-        .timeout(std::time::Duration::from_secs(30))
+        .timeout(config.auth_timeout())
         .send()
+        .map_err(|e| provider::timeout_hint(e, "auth_timeout_secs"))
         .context("Failed to exchange code")?;
 
     if !resp.status().is_success() {
@@ -100,14 +105,8 @@ fn exchange_code(code: &str, pkce: &PkceCodes) -> Result<Tokens> {
         .to_string();
 
     // Extract account_id from id_token claims
-    let account_id = parse_jwt_claims(&id_token)
-        .and_then(|claims| {
-            claims
-                .get("https://api.openai.com/auth")
-                .and_then(|auth| auth.get("chatgpt_account_id"))
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        })
+    let account_id = parse_claims(&id_token)
+        .and_then(|claims| claims.chatgpt_account_id().map(str::to_string))
         .unwrap_or_default();
 
     Ok(Tokens {
@@ -124,21 +123,24 @@ fn parse_request_query(request_line: &str) -> HashMap<String, String> {
         .split_whitespace()
         .nth(1)
         .and_then(|path| path.split_once('?'))
-        .map(|(_, query)| {
-            query
-                .split('&')
-                .filter_map(|pair| pair.split_once('='))
-                .map(|(k, v)| {
-                    (
-                        k.to_string(),
-                        urlencoding::decode(v).map(|s| s.into_owned()).unwrap_or_default(),
-                    )
-                })
-                .collect()
-        })
+        .map(|(_, query)| parse_query(query))
         .unwrap_or_default()
 }
 
+/// Decode `k=v&k2=v2` into a map.
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                urlencoding::decode(v).map(|s| s.into_owned()).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
 const SUCCESS_HTML: &str = r#"<html>
 <head><title>Login Successful</title></head>
 <body style="font-family: system-ui; max-width: 600px; margin: 80px auto;">
@@ -154,10 +156,95 @@ fn http_response(status: &str, body: &str) -> String {
     )
 }
 
-/// Block on a one-shot HTTP server until the OAuth callback delivers a code.
-fn wait_for_callback(listener: &TcpListener, pkce: &PkceCodes, state: &str) -> Result<Tokens> {
-    for stream in listener.incoming() {
-        let mut stream = stream?;
+const DENIED_HTML: &str = r#"<html>
+<head><title>Login Cancelled</title></head>
+<body style="font-family: system-ui; max-width: 600px; margin: 80px auto;">
+    <h1>Login Cancelled</h1>
+    <p>{reason}</p>
+    <p>You can close this window. Run <code>jose login</code> to try again.</p>
+</body>
+</html>"#;
+
+/// Minimal escaping for text placed in an HTML page.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// How a login attempt ended, short of an error.
+enum LoginOutcome {
+    Tokens(Tokens),
+    /// No callback arrived in time.
+    TimedOut,
+    /// The IdP redirected back with `?error=...`, e.g. the user clicked Cancel.
+    Denied(String),
+}
+
+/// The `error` / `error_description` of an OAuth error redirect, if any.
+fn redirect_error(params: &HashMap<String, String>) -> Option<String> {
+    let error = params.get("error")?;
+    Some(match params.get("error_description") {
+        Some(description) if !description.is_empty() => format!("{} ({})", description, error),
+        _ if error == "access_denied" => "Access was denied (access_denied)".to_string(),
+        _ => error.clone(),
+    })
+}
+
+/// What an OAuth redirect carried, once its `state` has been checked.
+#[derive(Debug, PartialEq)]
+enum Redirect {
+    Code(String),
+    /// See [`redirect_error`].
+    Denied(String),
+}
+
+/// Check the redirect's `state` against the one we sent, then pull out the
+/// code or the error. A missing `state` is rejected like a wrong one, so
+/// nothing reaches `exchange_code` without it.
+fn read_redirect(params: &HashMap<String, String>, state: &str) -> Result<Redirect> {
+    match params.get("state") {
+        Some(s) if s == state => {}
+        Some(_) => anyhow::bail!("OAuth state mismatch - possible CSRF, aborting."),
+        None => anyhow::bail!("OAuth state missing from the redirect - possible CSRF, aborting."),
+    }
+    if let Some(reason) = redirect_error(params) {
+        return Ok(Redirect::Denied(reason));
+    }
+    params
+        .get("code")
+        .map(|code| Redirect::Code(code.clone()))
+        .ok_or_else(|| anyhow::anyhow!("Missing authorization code in redirect"))
+}
+
+/// How often the callback listener checks for a connection or the deadline.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// Serve a one-shot HTTP server until the OAuth callback delivers a code or
+/// an error, or until `timeout` passes.
+fn wait_for_callback(
+    listener: &TcpListener,
+    pkce: &PkceCodes,
+    state: &str,
+    timeout: Duration,
+) -> Result<LoginOutcome> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline {
+                    return Ok(LoginOutcome::TimedOut);
+                }
+                std::thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
 
         let mut request_line = String::new();
         BufReader::new(&stream).read_line(&mut request_line)?;
@@ -170,32 +257,117 @@ fn wait_for_callback(listener: &TcpListener, pkce: &PkceCodes, state: &str) -> R
 
         let params = parse_request_query(&request_line);
 
-        if params.get("state").map(String::as_str) != Some(state) {
-            let _ = stream.write_all(
-                http_response("400 Bad Request", "<h1>Error</h1><p>State mismatch</p>").as_bytes(),
-            );
-            anyhow::bail!("OAuth state mismatch - possible CSRF, aborting.");
-        }
-
-        let code = params
-            .get("code")
-            .ok_or_else(|| anyhow::anyhow!("Missing authorization code in callback"))?;
-
-        let tokens = exchange_code(code, pkce)?;
+        let code = match read_redirect(&params, state) {
+            Ok(Redirect::Code(code)) => code,
+            Ok(Redirect::Denied(reason)) => {
+                let page = DENIED_HTML.replace("{reason}", &html_escape(&reason));
+                let _ = stream.write_all(http_response("200 OK", &page).as_bytes());
+                let _ = stream.flush();
+                return Ok(LoginOutcome::Denied(reason));
+            }
+            Err(e) => {
+                let page = format!("<h1>Error</h1><p>{}</p>", html_escape(&e.to_string()));
+                let _ = stream.write_all(http_response("400 Bad Request", &page).as_bytes());
+                return Err(e);
+            }
+        };
+
+        let tokens = exchange_code(&code, pkce)?;
         let _ = stream.write_all(http_response("200 OK", SUCCESS_HTML).as_bytes());
         let _ = stream.flush();
-        return Ok(tokens);
+        return Ok(LoginOutcome::Tokens(tokens));
+    }
+}
+
+/// Headless login: the user opens `auth_url` on any machine and pastes back
+/// the `localhost` URL the browser is redirected to (it won't load, but its
+/// address bar holds the code).
+fn manual_login(auth_url: &str, pkce: &PkceCodes, state: &str) -> Result<LoginOutcome> {
+    log::info("Open this URL in a browser on any machine and log in:");
+    log::prose(auth_url);
+    log::info(&format!(
+        "You'll end up on a page at localhost:{} that fails to load. Copy its full URL.",
+        OAUTH_PORT
+    ));
+    log::print_inline("Paste the URL (or just the code): ");
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    if input.is_empty() {
+        anyhow::bail!("No code entered");
     }
 
-    anyhow::bail!("Listener closed before receiving callback")
+    let code = if input.contains('=') {
+        let query = input.split_once('?').map_or(input, |(_, query)| query);
+        let params = parse_query(query.split('#').next().unwrap_or_default());
+        let redirect =
+            read_redirect(&params, state).context("Paste the full URL from this login attempt")?;
+        match redirect {
+            Redirect::Code(code) => code,
+            Redirect::Denied(reason) => return Ok(LoginOutcome::Denied(reason)),
+        }
+    } else {
+        // A bare code can't be matched to this login attempt.
+        log::warn(
+            "Using a bare code skips the OAuth state check. Only do this with a code \
+             from the URL you just opened.",
+        );
+        input.to_string()
+    };
+
+    Ok(LoginOutcome::Tokens(exchange_code(&code, pkce)?))
 }
 
-pub fn do_login() -> Result<bool> {
+pub fn do_login(manual: bool) -> Result<bool> {
     log::info("Starting OAuth login flow...");
-    log::dim(&format!("Note: Make sure port {} is not in use", OAUTH_PORT));
 
     let pkce = PkceCodes::generate();
     let state_token: String = Alphanumeric.sample_string(&mut rand::rng(), 64);
+    let config = Config::load()?;
+    let auth_url = build_auth_url(&config, &pkce, &state_token);
+
+    let timeout = config.login_timeout();
+    let outcome = if manual {
+        manual_login(&auth_url, &pkce, &state_token)?
+    } else {
+        browser_login(&auth_url, &pkce, &state_token, timeout)?
+    };
+    let tokens = match outcome {
+        LoginOutcome::Tokens(tokens) => tokens,
+        LoginOutcome::TimedOut => {
+            log::error(&format!(
+                "No login callback within {}s; giving up. Run `jose login` to try again \
+                 (or raise `login_timeout_secs`).",
+                timeout.as_secs()
+            ));
+            return Ok(false);
+        }
+        LoginOutcome::Denied(reason) => {
+            log::error(&format!("Login was not completed: {}", reason));
+            return Ok(false);
+        }
+    };
+
+    let auth = AuthData {
+        tokens,
+        last_refresh: chrono::Utc::now().to_rfc3339(),
+    };
+    auth.save()?;
+    log::success("Login successful! Credentials saved.");
+
+    verify_login(&config, &auth);
+    Ok(true)
+}
+
+/// Open `auth_url` in a local browser and catch the redirect on `OAUTH_PORT`.
+fn browser_login(
+    auth_url: &str,
+    pkce: &PkceCodes,
+    state_token: &str,
+    timeout: Duration,
+) -> Result<LoginOutcome> {
+    log::dim(&format!("Note: Make sure port {} is not in use", OAUTH_PORT));
 
     let addr = format!("127.0.0.1:{}", OAUTH_PORT);
     let listener = match TcpListener::bind(&addr) {
@@ -207,24 +379,79 @@ pub fn do_login() -> Result<bool> {
         }
     };
 
-    let auth_url = build_auth_url(&pkce, &state_token);
-
     log::info("Opening browser for authentication...");
     log::dim(&format!("If browser doesn't open, visit:\n{}", auth_url));
+    log::dim("(No browser on this machine? Use `jose login --manual`.)");
 
-    if let Err(e) = open::that(&auth_url) {
+    if let Err(e) = open::that(auth_url) {
         log::warn(&format!("Failed to open browser: {}", e));
     }
 
     log::info("Waiting for authentication callback...");
 
-    let tokens = wait_for_callback(&listener, &pkce, &state_token)?;
+    wait_for_callback(&listener, pkce, state_token, timeout)
+}
 
-    let auth = AuthData {
-        tokens,
-        last_refresh: chrono::Utc::now().to_rfc3339(),
-    };
-    auth.save()?;
-    log::success("Login successful! Credentials saved.");
-    Ok(true)
+/// Confirm the fresh tokens actually work now, rather than on first use.
+fn verify_login(config: &Config, auth: &AuthData) {
+    log::info("Verifying access...");
+    let who = auth.describe().who();
+
+    match provider::verify_chatgpt_access(config) {
+        Ok(()) => log::success(&format!("Verified access for {}", who)),
+        Err(e) => {
+            log::warn(&format!(
+                "Logged in as {}, but a test request failed: {}",
+                who, e
+            ));
+            log::info(
+                "If your ChatGPT account belongs to several workspaces, make sure the one \
+                 you picked has Codex access, then run `jose login` again.",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_state_yields_code() {
+        assert_eq!(
+            read_redirect(&parse_query("code=abc&state=s1"), "s1").unwrap(),
+            Redirect::Code("abc".to_string())
+        );
+    }
+
+    #[test]
+    fn mismatched_state_is_rejected() {
+        let err = read_redirect(&parse_query("code=abc&state=other"), "s1").unwrap_err();
+        assert!(err.to_string().contains("state mismatch"));
+    }
+
+    #[test]
+    fn missing_state_is_rejected() {
+        let err = read_redirect(&parse_query("code=abc"), "s1").unwrap_err();
+        assert!(err.to_string().contains("state missing"));
+    }
+
+    #[test]
+    fn state_is_checked_before_errors() {
+        // A forged error redirect must not be reported as the user's choice.
+        assert!(read_redirect(&parse_query("error=access_denied"), "s1").is_err());
+        assert_eq!(
+            read_redirect(&parse_query("error=access_denied&state=s1"), "s1").unwrap(),
+            Redirect::Denied("Access was denied (access_denied)".to_string())
+        );
+    }
+
+    #[test]
+    fn callback_request_line_is_parsed() {
+        let line = "GET /auth/callback?code=a%2Fb&state=s1 HTTP/1.1\r\n";
+        assert_eq!(
+            read_redirect(&parse_request_query(line), "s1").unwrap(),
+            Redirect::Code("a/b".to_string())
+        );
+    }
 }
@@ -0,0 +1,69 @@
+//! Where jose keeps its files.
+//!
+//! With `$XDG_CONFIG_HOME` / `$XDG_DATA_HOME` set, the config goes under
+//! `$XDG_CONFIG_HOME/jose` and credentials, history and caches under
+//! `$XDG_DATA_HOME/jose`. Otherwise everything stays in `~/.jose`. A file
+//! still sitting in `~/.jose` is moved to its XDG location on first use.
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::log;
+
+/// `~/.jose`, the original home of every file.
+fn legacy_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home.join(".jose"))
+}
+
+/// `$<var>/jose`, if `var` holds an absolute path (the spec says to ignore
+/// relative ones).
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os(var)?);
+    dir.is_absolute().then(|| dir.join("jose"))
+}
+
+/// Path of `file_name` under the XDG directory named by `var`, or `~/.jose`.
+fn resolve(var: &str, file_name: &str) -> Result<PathBuf> {
+    let legacy = legacy_dir()?.join(file_name);
+    let Some(dir) = xdg_dir(var) else {
+        return Ok(legacy);
+    };
+    let path = dir.join(file_name);
+    if !path.exists() && legacy.exists() {
+        match migrate(&legacy, &path) {
+            // This can run before `--json` silences stdout, and `config get`
+            // output is read by scripts, so the notice goes to stderr.
+            Ok(()) => log::warn(&format!("Moved {} to {}", legacy.display(), path.display())),
+            // Keep using the old file rather than starting from scratch.
+            Err(e) => {
+                log::warn(&format!("Failed to move {}: {}", legacy.display(), e));
+                return Ok(legacy);
+            }
+        }
+    }
+    Ok(path)
+}
+
+fn migrate(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // `rename` fails across filesystems; copying keeps the permissions.
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// A file in the config directory (`config.json`).
+pub fn config_file(file_name: &str) -> Result<PathBuf> {
+    resolve("XDG_CONFIG_HOME", file_name)
+}
+
+/// A file in the data directory (credentials, history, caches).
+pub fn data_file(file_name: &str) -> Result<PathBuf> {
+    resolve("XDG_DATA_HOME", file_name)
+}
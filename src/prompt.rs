@@ -1,12 +1,22 @@
-//! Shared system prompt for command generation.
+//! Shared system prompts for command generation and the other modes.
 
+use crate::config::Config;
 use crate::shell::SystemInfo;
 
-/// Build the system prompt, grounded in a probe of the host environment so the
-/// model emits commands with the correct flag syntax for this OS/shell/userland.
-pub fn build_system_prompt() -> String {
-    let sys = SystemInfo::gather();
+/// Prepend the organization-mandated `mandatory_preamble`, if configured.
+///
+/// Applied to every request's instructions regardless of which prompt is in
+/// use, so no per-invocation customization can drop it.
+pub fn with_preamble(config: &Config, instructions: String) -> String {
+    match config.mandatory_preamble.as_deref().map(str::trim) {
+        Some(preamble) if !preamble.is_empty() => format!("{}\n\n{}", preamble, instructions),
+        _ => instructions,
+    }
+}
 
+/// Bullet list describing the host environment, shared by every prompt so
+/// answers are grounded in the right OS/shell/userland.
+fn environment(sys: &SystemInfo) -> String {
     let os = match &sys.os_version {
         Some(v) => format!("{} {}", sys.os, v),
         None => sys.os.to_string(),
@@ -18,25 +28,168 @@ pub fn build_system_prompt() -> String {
     };
 
     format!(
-        r##"You are an expert command-line assistant. Generate shell commands for this EXACT environment:
-- OS: {os} ({arch})
+        r##"- OS: {os} ({arch})
 - Shell: {shell}
 - Core utilities: {coreutils} (flag syntax for sed, find, date, stat, xargs, readlink differs between GNU and BSD — use the {coreutils} form)
-- Package managers available: {pkg}
+- Package managers available: {pkg}"##,
+        os = os,
+        arch = sys.arch,
+        shell = sys.shell.name(),
+        coreutils = sys.coreutils,
+        pkg = pkg,
+    )
+}
+
+/// Default command-generation prompt. `command_system_prompt` in the config
+/// replaces it; see [`build_system_prompt`] for the placeholders.
+pub const DEFAULT_COMMAND_PROMPT: &str = r##"You are an expert command-line assistant. Generate shell commands for this EXACT environment:
+{env}
 
 Rules:
 - Output ONLY runnable command(s) — no prose, no markdown, no backticks, no comments.
-- Put the single best command on the FIRST line. Optional alternatives go on later lines, one command per line.
+- Put the single best command FIRST. Optional alternatives follow, one command per line. Only split a command across lines when it genuinely needs it (heredoc, multi-line loop).
 - Target the shell and OS above exactly. Use {shell} syntax and the correct {coreutils} flags; do not assume GNU options on BSD or vice versa.
 - Prefer tools already present. If something must be installed, use one of the available package managers above; never invent a package manager that is not listed.
 - Be non-interactive by default (avoid commands that prompt) and quote paths that may contain spaces.
 - Do not use sudo unless the task strictly requires elevated privileges.
 - If the request is destructive (deletes or overwrites data), still output the command but keep it minimal and tightly scoped.
-- If the task cannot be accomplished with a shell command on this system, output a single line starting with "# " that briefly explains why."##,
-        os = os,
-        arch = sys.arch,
+- If the task cannot be accomplished with a shell command on this system, output a single line starting with "# " that briefly explains why."##;
+
+/// Build the system prompt, grounded in a probe of the host environment so the
+/// model emits commands with the correct flag syntax for this OS/shell/userland.
+///
+/// The template (`command_system_prompt`, or [`DEFAULT_COMMAND_PROMPT`]) may
+/// use `{os}`, `{shell}`, `{arch}`, `{coreutils}` and `{env}` (the full
+/// environment bullet list).
+pub fn build_system_prompt(config: &Config) -> String {
+    let sys = SystemInfo::gather();
+    let template = config
+        .command_system_prompt
+        .as_deref()
+        .unwrap_or(DEFAULT_COMMAND_PROMPT);
+
+    template
+        .replace("{env}", &environment(&sys))
+        .replace("{os}", sys.os)
+        .replace("{shell}", sys.shell.name())
+        .replace("{arch}", sys.arch)
+        .replace("{coreutils}", sys.coreutils)
+}
+
+/// System prompt for `jose diff-explain`: compare two commands and answer
+/// with a small JSON object so the result can be rendered consistently.
+pub fn build_diff_prompt() -> String {
+    let sys = SystemInfo::gather();
+
+    format!(
+        r##"You are an expert command-line assistant. The user gives you two shell commands, A and B, meant to run in this environment:
+{env}
+
+Compare them and reply with ONLY a JSON object (no markdown, no backticks) of this shape:
+{{"summary": "<one or two sentences on how A and B differ overall>", "differences": ["<one concrete difference per item: a flag, behavior, or edge case>"], "safer": "A" | "B" | "same", "faster": "A" | "B" | "same", "recommendation": "<which to use and when, one sentence>"}}
+
+Judge flags by the {shell} shell and {coreutils} userland above. Call out anything destructive or irreversible."##,
+        env = environment(&sys),
+        shell = sys.shell.name(),
+        coreutils = sys.coreutils,
+    )
+}
+
+/// System prompt for the safety check: classify one command as destructive
+/// or not, answering with a small JSON object.
+pub fn build_danger_prompt() -> String {
+    let sys = SystemInfo::gather();
+
+    format!(
+        r##"You review shell commands before a user runs them in this environment:
+{env}
+
+Decide whether the given command is destructive: it deletes, overwrites or truncates data (e.g. rm -rf, dd, mkfs, shred, > redirects onto existing files, git push --force, git reset --hard, DROP TABLE), changes permissions or ownership recursively, kills processes broadly, or is otherwise hard to undo. Read-only and easily reversible commands are not destructive.
+
+Reply with ONLY a JSON object (no markdown, no backticks): {{"destructive": true | false, "reason": "<short phrase naming what would be lost or changed, or empty>"}}"##,
+        env = environment(&sys),
+    )
+}
+
+/// System prompt for `jose explain`: break a command down for this shell.
+pub fn build_explain_prompt() -> String {
+    let sys = SystemInfo::gather();
+
+    format!(
+        r##"You are an expert command-line assistant. The user gives you one shell command meant to run in this environment:
+{env}
+
+Explain it concisely in plain text (no markdown, no code fences):
+- First, one sentence on what the command does as a whole.
+- Then one line per part that matters ("- <part>: <what it does>"): each program in a pipeline, and each flag or argument whose meaning is not obvious. Judge flags by the {shell} shell and {coreutils} userland above.
+- Finally, if anything is risky (deletes or overwrites data, needs root, touches the network, is irreversible, or behaves differently than it looks), a line starting with "Risks:" naming it. Omit that line if there is nothing risky.
+- If the input is not a valid command for this shell, say so and why."##,
+        env = environment(&sys),
         shell = sys.shell.name(),
         coreutils = sys.coreutils,
-        pkg = pkg,
     )
 }
+
+/// System prompt for `jose summarize`: condense logs or command output.
+pub fn build_summary_prompt(bullets: bool, long: bool) -> String {
+    let format = if bullets {
+        "Answer as a plain bullet list (lines starting with \"- \"), no headings."
+    } else {
+        "Answer in plain prose, no headings."
+    };
+    let length = if long {
+        "Be thorough: up to about 15 sentences or bullets, covering every distinct issue."
+    } else {
+        "Be brief: at most 5 sentences or bullets."
+    };
+
+    format!(
+        r##"You summarize logs and command output for a developer in a terminal. The input may be truncated at the start.
+- Lead with what matters most: errors, failures, and their likely cause; then warnings; then notable normal activity.
+- Quote exact error messages, unit names, file paths, and timestamps when they help; never invent details that are not in the input.
+- Collapse repeated lines into one mention with a count.
+- {format}
+- {length}
+- Do not use markdown emphasis or code fences."##,
+        format = format,
+        length = length,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preamble_survives_custom_prompt() {
+        let config = Config {
+            mandatory_preamble: Some("Never include secrets.\n".to_string()),
+            command_system_prompt: Some("Answer for {shell} only.".to_string()),
+            ..Config::default()
+        };
+        let prompt = with_preamble(&config, build_system_prompt(&config));
+        assert!(prompt.starts_with("Never include secrets.\n\nAnswer for "));
+        assert!(!prompt.contains("{shell}"));
+    }
+
+    #[test]
+    fn preamble_applies_to_every_mode() {
+        let config = Config {
+            mandatory_preamble: Some("Never include secrets.".to_string()),
+            ..Config::default()
+        };
+        for instructions in [build_danger_prompt(), build_diff_prompt()] {
+            let expected = format!("Never include secrets.\n\n{}", instructions);
+            assert_eq!(with_preamble(&config, instructions), expected);
+        }
+    }
+
+    #[test]
+    fn blank_preamble_is_ignored() {
+        let config = Config {
+            mandatory_preamble: Some("  \n".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(with_preamble(&config, "Be brief.".to_string()), "Be brief.");
+    }
+}
@@ -1,83 +1,701 @@
 //! Command-generation backends behind a single entrypoint.
 
 use anyhow::{Context, Result};
-use std::io::{BufRead, BufReader};
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
 use std::time::Duration;
 
-use crate::auth::get_valid_tokens;
-use crate::config::{Config, ProviderKind, CHATGPT_RESPONSES_URL};
-use crate::prompt::build_system_prompt;
+use crate::attachment::ImageAttachment;
+use crate::auth::{get_valid_tokens, Tokens};
+use crate::config::{Config, ProviderKind, ReasoningEffort, DEFAULT_MODEL};
+use crate::http;
+use crate::jwt::parse_claims;
+use crate::log;
+use crate::prompt::{build_danger_prompt, build_diff_prompt, build_system_prompt, with_preamble};
+use crate::sse;
+
+/// Per-request extras on top of the prompt. Unset fields are left out of the
+/// payload so the provider's defaults apply.
+#[derive(Clone, Copy, Default)]
+pub struct RequestOptions<'a> {
+    pub image: Option<&'a ImageAttachment>,
+    /// Sampling seed. Reproducibility is best-effort: not every model honours it.
+    pub seed: Option<u64>,
+    pub temperature: Option<f32>,
+    /// Only sent to gpt-5 models; others reject the parameter.
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl RequestOptions<'_> {
+    /// Add the optional sampling fields to a request `payload`.
+    fn apply(&self, payload: &mut serde_json::Value) {
+        if let Some(seed) = self.seed {
+            payload["seed"] = seed.into();
+        }
+        if let Some(temperature) = self.temperature {
+            payload["temperature"] = temperature.into();
+        }
+        let gpt5 = payload["model"]
+            .as_str()
+            .is_some_and(|model| model.starts_with("gpt-5"));
+        if let Some(effort) = self.reasoning_effort.filter(|_| gpt5) {
+            // Chat Completions takes a flat field; the Responses API nests it.
+            if payload.get("messages").is_some() {
+                payload["reasoning_effort"] = effort.as_str().into();
+            } else {
+                payload["reasoning"] = serde_json::json!({"effort": effort.as_str()});
+            }
+        }
+    }
+}
 
 /// Generate command suggestions for `prompt` using the configured provider.
-pub fn generate(config: &Config, prompt: &str, model: &str) -> Result<String> {
-    let system_prompt = build_system_prompt();
+pub fn generate(
+    config: &Config,
+    prompt: &str,
+    model: &str,
+    opts: &RequestOptions,
+) -> Result<String> {
+    generate_streaming(config, prompt, model, opts, &mut |_| {})
+}
+
+/// Like [`generate`], but calls `on_delta` with each chunk of text as it
+/// arrives. Non-streaming providers deliver the whole answer in one chunk.
+pub fn generate_streaming(
+    config: &Config,
+    prompt: &str,
+    model: &str,
+    opts: &RequestOptions,
+    on_delta: &mut dyn FnMut(&str),
+) -> Result<String> {
+    let instructions = build_system_prompt(config);
+    complete_streaming(config, instructions, prompt, model, opts, on_delta)
+}
+
+/// Send `prompt` under arbitrary `instructions` using the configured provider.
+/// The mandatory preamble is always prepended.
+pub fn complete(
+    config: &Config,
+    instructions: String,
+    prompt: &str,
+    model: &str,
+    opts: &RequestOptions,
+) -> Result<String> {
+    complete_streaming(config, instructions, prompt, model, opts, &mut |_| {})
+}
+
+fn complete_streaming(
+    config: &Config,
+    instructions: String,
+    prompt: &str,
+    model: &str,
+    opts: &RequestOptions,
+    on_delta: &mut dyn FnMut(&str),
+) -> Result<String> {
+    let system_prompt = with_preamble(config, instructions);
+    let opts = &RequestOptions {
+        reasoning_effort: opts.reasoning_effort.or(config.reasoning_effort),
+        ..*opts
+    };
     match config.provider {
-        ProviderKind::Chatgpt => call_chatgpt(prompt, model, &system_prompt),
-        ProviderKind::OpenAiCompatible => call_openai_compatible(config, prompt, model, &system_prompt),
+        ProviderKind::Chatgpt => {
+            call_chatgpt(config, prompt, model, &system_prompt, opts, on_delta)
+        }
+        ProviderKind::OpenAiCompatible => {
+            let text = call_openai_compatible(config, prompt, model, &system_prompt, opts)?;
+            on_delta(&text);
+            Ok(text)
+        }
+    }
+}
+
+/// Structured comparison returned by [`explain_diff`].
+#[derive(Debug, Default, Deserialize)]
+pub struct DiffExplanation {
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub differences: Vec<String>,
+    #[serde(default)]
+    pub safer: Option<String>,
+    #[serde(default)]
+    pub faster: Option<String>,
+    #[serde(default)]
+    pub recommendation: Option<String>,
+}
+
+/// Ask the model to compare commands `a` and `b` for this environment.
+///
+/// If the model ignores the JSON format, its raw answer becomes the summary.
+pub fn explain_diff(config: &Config, a: &str, b: &str, model: &str) -> Result<DiffExplanation> {
+    let prompt = format!("Command A:\n{}\n\nCommand B:\n{}", a, b);
+    let opts = RequestOptions::default();
+    let text = complete(config, build_diff_prompt(), &prompt, model, &opts)?;
+
+    let parsed = serde_json::from_str(strip_json_fence(&text));
+    Ok(parsed.unwrap_or_else(|_| DiffExplanation {
+        summary: text,
+        ..Default::default()
+    }))
+}
+
+/// Tolerate a stray ```json fence around a JSON answer.
+fn strip_json_fence(text: &str) -> &str {
+    text.trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+}
+
+/// Verdict returned by [`check_danger`].
+#[derive(Debug, Default, Deserialize)]
+pub struct DangerVerdict {
+    #[serde(default)]
+    pub destructive: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Ask the model whether `command` is destructive. A second, cheap request:
+/// gpt-5 models are asked for minimal reasoning. An answer that isn't the
+/// expected JSON counts as not destructive.
+pub fn check_danger(config: &Config, command: &str, model: &str) -> Result<DangerVerdict> {
+    let opts = RequestOptions {
+        reasoning_effort: Some(ReasoningEffort::Minimal),
+        ..Default::default()
+    };
+    let text = complete(config, build_danger_prompt(), command, model, &opts)?;
+    Ok(serde_json::from_str(strip_json_fence(&text)).unwrap_or_default())
+}
+
+/// A non-success HTTP response from a provider.
+#[derive(Debug)]
+pub struct ApiError {
+    /// Who answered, e.g. `ChatGPT`; used to label the message.
+    pub source: String,
+    pub status: StatusCode,
+    pub body: String,
+    /// How long the server asked us to back off (`Retry-After`/`x-ratelimit-reset-*`).
+    pub retry_after: Option<Duration>,
+}
+
+/// The `{"error": {...}}` object OpenAI-style APIs return on failure.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+impl fmt::Display for ApiError {
+    /// `ChatGPT error (invalid_request_error): model not found`, or the raw
+    /// body when it isn't a structured error.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ok(ErrorBody { error }) = serde_json::from_str(&self.body) else {
+            return write!(f, "API error: {} - {}", self.status, self.body);
+        };
+        let code = match error.code {
+            Some(serde_json::Value::String(code)) => Some(code),
+            Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+            _ => None,
+        };
+        let kind = error
+            .kind
+            .or(code)
+            .unwrap_or_else(|| self.status.to_string());
+        write!(f, "{} error ({}): {}", self.source, kind, error.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Pass successful responses through; turn anything else into an [`ApiError`]
+/// attributed to `source`.
+fn check_status(resp: Response, source: &str) -> Result<Response> {
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    let retry_after = retry_after(resp.headers());
+    let body = resp.text().unwrap_or_default();
+    Err(ApiError {
+        source: source.to_string(),
+        status,
+        body,
+        retry_after,
+    }
+    .into())
+}
+
+/// Longest server-requested wait we sit through before giving up.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+
+/// First backoff delay; doubles on every further attempt.
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+const RATE_LIMIT_HINT: &str = "Try again later or use another model with --model.";
+
+/// Send `req`, retrying transient failures (429 and 500/502/503/504) up to
+/// `max_retries` times. The server's back-off hint is honoured when present,
+/// otherwise a jittered exponential backoff is used. Other statuses fail fast.
+fn send(req: RequestBuilder, target: &str, max_retries: u32) -> Result<Response> {
+    // Servers reached by URL are labelled generically in error messages.
+    let source = Some(target).filter(|t| !t.contains("://")).unwrap_or("API");
+    let mut attempt = 0;
+    loop {
+        let resp = req
+            .try_clone()
+            .context("Request body cannot be retried")?
+            .send()
+            .map_err(|e| timeout_hint(e, "request_timeout_secs"))
+            .with_context(|| format!("Failed to send request to {}", target))?;
+        let err = match check_status(resp, source) {
+            Ok(resp) => return Ok(resp),
+            Err(err) => err,
+        };
+
+        let Some(api) = err.downcast_ref::<ApiError>() else {
+            return Err(err);
+        };
+        let rate_limited = api.status == StatusCode::TOO_MANY_REQUESTS;
+        let retryable = rate_limited || matches!(api.status.as_u16(), 500 | 502 | 503 | 504);
+        if !retryable {
+            return Err(err);
+        }
+
+        let wait = api.retry_after.unwrap_or_else(|| backoff(attempt));
+        if wait > MAX_RATE_LIMIT_WAIT {
+            if !rate_limited {
+                let msg = format!("The server asked to retry in {}.", format_wait(wait));
+                return Err(err.context(msg));
+            }
+            let msg = format!(
+                "Rate limited; the limit resets in {}. {}",
+                format_wait(wait),
+                RATE_LIMIT_HINT
+            );
+            return Err(err.context(msg));
+        }
+        if attempt >= max_retries {
+            if rate_limited {
+                return Err(err.context(format!("Rate limited. {}", RATE_LIMIT_HINT)));
+            }
+            return Err(err);
+        }
+
+        attempt += 1;
+        let reason = if rate_limited {
+            "Rate limited".to_string()
+        } else {
+            format!("Server error ({})", api.status.as_u16())
+        };
+        log::warn(&format!(
+            "{}, retrying in {} ({}/{})...",
+            reason,
+            format_delay(wait),
+            attempt,
+            max_retries
+        ));
+        std::thread::sleep(wait);
+    }
+}
+
+/// Exponential backoff with jitter: a random delay in `[d/2, d]` where
+/// `d = BASE_RETRY_DELAY * 2^attempt`.
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_RETRY_DELAY * 2u32.saturating_pow(attempt.min(10));
+    delay.mul_f64(rand::random_range(0.5..=1.0))
+}
+
+/// Short human-readable delay, e.g. `750ms` or `2s`.
+fn format_delay(wait: Duration) -> String {
+    if wait < Duration::from_secs(1) {
+        format!("{}ms", wait.as_millis())
+    } else {
+        format!("{}s", wait.as_secs())
+    }
+}
+
+/// Turn a reqwest timeout into an error naming the config `setting` to raise;
+/// other errors pass through unchanged.
+pub fn timeout_hint(err: reqwest::Error, setting: &str) -> anyhow::Error {
+    if err.is_timeout() {
+        let file = Config::config_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| "config.json".to_string());
+        let hint = format!(
+            "Request timed out. Raise `{}` in {} on slow connections.",
+            setting, file
+        );
+        anyhow::Error::new(err).context(hint)
+    } else {
+        err.into()
+    }
+}
+
+/// Parse the back-off hint from `Retry-After` (seconds or HTTP date), falling
+/// back to the longest `x-ratelimit-reset-*` window (e.g. `6m0s`, `20ms`).
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.trim().parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            let secs = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+            return Some(Duration::from_secs(secs as u64));
+        }
+    }
+
+    ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .iter()
+        .filter_map(|name| header(name).and_then(parse_reset))
+        .max()
+}
+
+/// Parse a Go-style duration as used by `x-ratelimit-reset-*`: `1h2m3.5s`, `20ms`.
+fn parse_reset(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let seconds = match &rest[..unit_len] {
+            "ms" => number / 1000.0,
+            "s" | "" => number,
+            "m" => number * 60.0,
+            "h" => number * 3600.0,
+            _ => return None,
+        };
+        total += seconds;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+/// Human-readable wait, e.g. `45s` or `6m 0s`.
+fn format_wait(wait: Duration) -> String {
+    let secs = wait.as_secs();
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs.max(1))
+    }
+}
+
+/// Whether `err` is worth retrying on a different model: a timeout, or an API
+/// error that points at the model itself (unknown, overloaded, unavailable).
+pub fn should_fall_back(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout();
+        }
+        if let Some(e) = cause.downcast_ref::<ApiError>() {
+            return e.status == StatusCode::NOT_FOUND
+                || e.status.is_server_error()
+                || (e.status.is_client_error() && e.body.to_lowercase().contains("model"));
+        }
+        false
+    })
+}
+
+/// Final `response` object of a Responses API stream, plus the accumulated text.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatgptResponse {
+    /// Response id, for chaining a follow-up via `previous_response_id`.
+    #[serde(default)]
+    pub id: String,
+    /// The model that actually answered, which may be a dated snapshot.
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub output: Vec<OutputItem>,
+    /// Text accumulated from the `output_text` deltas.
+    #[serde(skip)]
+    pub text: String,
+}
+
+/// Token counts of a response.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutputItem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub content: Vec<ContentPart>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentPart {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl ChatgptResponse {
+    /// Concatenated `output_text` parts of the final assistant messages.
+    fn output_text(&self) -> String {
+        self.output
+            .iter()
+            .filter(|item| item.kind == "message")
+            .filter(|item| item.role.as_deref().is_none_or(|role| role == "assistant"))
+            .flat_map(|item| &item.content)
+            .filter(|part| part.kind == "output_text")
+            .filter_map(|part| part.text.as_deref())
+            .collect()
     }
 }
 
 /// ChatGPT subscription backend: OAuth bearer + streaming Responses API.
-fn call_chatgpt(prompt: &str, model: &str, system_prompt: &str) -> Result<String> {
-    let tokens = get_valid_tokens()?
+fn call_chatgpt(
+    config: &Config,
+    prompt: &str,
+    model: &str,
+    system_prompt: &str,
+    opts: &RequestOptions,
+    on_delta: &mut dyn FnMut(&str),
+) -> Result<String> {
+    let response = call_chatgpt_streaming(config, prompt, model, system_prompt, opts, on_delta)?;
+    Ok(response.text.trim().to_string())
+}
+
+/// Like [`call_chatgpt`], but returns the structured final response object.
+pub fn call_chatgpt_full(
+    config: &Config,
+    prompt: &str,
+    model: &str,
+    system_prompt: &str,
+    opts: &RequestOptions,
+) -> Result<ChatgptResponse> {
+    call_chatgpt_streaming(config, prompt, model, system_prompt, opts, &mut |_| {})
+}
+
+/// Like [`call_chatgpt_full`], calling `on_delta` with each text delta as it
+/// arrives on the stream.
+pub fn call_chatgpt_streaming(
+    config: &Config,
+    prompt: &str,
+    model: &str,
+    system_prompt: &str,
+    opts: &RequestOptions,
+    on_delta: &mut dyn FnMut(&str),
+) -> Result<ChatgptResponse> {
+    let tokens = get_valid_tokens(config)?
         .ok_or_else(|| anyhow::anyhow!("Not authenticated. Run `jose login` first."))?;
+    let streaming = config.streaming;
 
-    let payload = serde_json::json!({
+    let mut payload = serde_json::json!({
         "model": model,
         "instructions": system_prompt,
-        "input": [{"role": "user", "content": prompt}],
+        "input": [{"role": "user", "content": chatgpt_content(prompt, opts.image)}],
         "tools": [],
         "tool_choice": "auto",
         "parallel_tool_calls": false,
         "store": false,
-        "stream": true,
+        "stream": streaming,
     });
+    opts.apply(&mut payload);
 
-    let resp = reqwest::blocking::Client::new()
-        .post(CHATGPT_RESPONSES_URL)
-        .header("Authorization", format!("Bearer {}", tokens.access_token))
-        .header("Content-Type", "application/json")
-        .header("Accept", "text/event-stream")
-        .header("chatgpt-account-id", &tokens.account_id)
-        .header("OpenAI-Beta", "responses=experimental")
-        .json(&payload)
-        .timeout(Duration::from_secs(120))
-        .send()
-        .context("Failed to send request to ChatGPT")?;
+    let req = chatgpt_request(config, &tokens, &payload)?;
+    let resp = send(req, "ChatGPT", config.max_retries)?;
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().unwrap_or_default();
-        anyhow::bail!("API error: {} - {}", status, body);
+    let response = if streaming {
+        read_stream(BufReader::new(resp), on_delta)?
+    } else {
+        let mut response: ChatgptResponse = resp.json().context("Invalid response from ChatGPT")?;
+        response.text = response.output_text();
+        on_delta(&response.text);
+        response
+    };
+    if let Some(usage) = response.usage {
+        log::debug(&format!(
+            "Response {} from {}: {} input + {} output = {} tokens",
+            response.id,
+            response.model,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.total_tokens
+        ));
     }
+    Ok(response)
+}
 
-    // Parse SSE stream
+/// Collect a Responses API event stream into the final response, calling
+/// `on_delta` with each text delta as it arrives.
+fn read_stream(reader: impl BufRead, on_delta: &mut dyn FnMut(&str)) -> Result<ChatgptResponse> {
     let mut out = String::new();
-    for line in BufReader::new(resp).lines() {
-        let line = line?;
-        let Some(data) = line.strip_prefix("data: ") else {
-            continue;
-        };
-        if data == "[DONE]" {
+    let mut response = ChatgptResponse::default();
+    for event in sse::Events::new(reader) {
+        let event = event?;
+        if event.data == "[DONE]" {
             break;
         }
-        let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else {
-            continue;
-        };
-        if event.get("type") == Some(&serde_json::json!("response.output_text.delta")) {
-            if let Some(delta) = event.get("delta").and_then(|d| d.as_str()) {
-                out.push_str(delta);
-            }
-        } else if let Some(delta) = event.get("delta") {
-            if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
-                out.push_str(text);
-            } else if let Some(text) = delta.as_str() {
+        for event in parse_event_data(&event) {
+            let kind = event
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default();
+            if matches!(kind, "response.completed" | "response.incomplete") {
+                if let Some(final_response) = event.get("response") {
+                    match serde_json::from_value(final_response.clone()) {
+                        Ok(parsed) => response = parsed,
+                        Err(e) => {
+                            log::warn(&format!("Ignoring invalid final response object: {}", e))
+                        }
+                    }
+                }
+            } else if kind.starts_with("response.reasoning") {
+                // Reasoning models (gpt-5-codex) stream their thinking too; a
+                // one-shot answer has no use for it.
+                continue;
+            } else if let Some(delta) = event.get("delta") {
+                let text = delta.get("text").and_then(|t| t.as_str());
+                let Some(text) = text.or_else(|| delta.as_str()) else {
+                    continue;
+                };
                 out.push_str(text);
+                on_delta(text);
             }
         }
     }
 
-    Ok(out.trim().to_string())
+    // Deltas are authoritative; fall back to the final output items if none arrived.
+    response.text = if out.is_empty() {
+        response.output_text()
+    } else {
+        out
+    };
+    Ok(response)
+}
+
+/// JSON payloads of an SSE event. Some servers break one object over several
+/// `data:` lines mid-string, others send one object per line with no blank
+/// line between them; if the joined lines don't parse, try them
+/// concatenated, then every line that parses on its own, in order.
+fn parse_event_data(event: &sse::Event) -> Vec<serde_json::Value> {
+    if let Ok(value) = serde_json::from_str(&event.data)
+        .or_else(|_| serde_json::from_str(&event.lines().collect::<String>()))
+    {
+        return vec![value];
+    }
+    event
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// User message content for the Responses API: plain text, or text plus an
+/// `input_image` part when an image is attached.
+fn chatgpt_content(prompt: &str, image: Option<&ImageAttachment>) -> serde_json::Value {
+    match image {
+        None => serde_json::json!(prompt),
+        Some(image) => serde_json::json!([
+            {"type": "input_text", "text": prompt},
+            {"type": "input_image", "image_url": image.data_url},
+        ]),
+    }
+}
+
+/// Make the smallest possible request with the stored credentials to confirm
+/// the access token and `chatgpt-account-id` are accepted by the backend.
+pub fn verify_chatgpt_access(config: &Config) -> Result<()> {
+    let instructions = with_preamble(config, "Reply with the single word OK.".to_string());
+    let opts = RequestOptions::default();
+    call_chatgpt_full(config, "ping", DEFAULT_MODEL, &instructions, &opts)?;
+    Ok(())
+}
+
+/// POST `payload` to the Responses endpoint with the ChatGPT auth headers.
+fn chatgpt_request(
+    config: &Config,
+    tokens: &Tokens,
+    payload: &serde_json::Value,
+) -> Result<RequestBuilder> {
+    let url = config.responses_url();
+    let accept = match payload["stream"].as_bool() {
+        Some(false) => "application/json",
+        _ => "text/event-stream",
+    };
+    log::debug(&format!(
+        "POST {} (model {}, token {})",
+        url,
+        payload["model"].as_str().unwrap_or("?"),
+        token_expiry(tokens)
+    ));
+    Ok(http::client()?
+        .post(url)
+        .header("Authorization", format!("Bearer {}", tokens.access_token))
+        .header("Content-Type", "application/json")
+        .header("Accept", accept)
+        .header("chatgpt-account-id", &tokens.account_id)
+        .header("OpenAI-Beta", "responses=experimental")
+        .json(payload)
+        .timeout(config.request_timeout()))
+}
+
+/// When the access token expires, for `--verbose`; never the token itself.
+fn token_expiry(tokens: &Tokens) -> String {
+    match parse_claims(&tokens.access_token).and_then(|c| c.expires_at()) {
+        Some(exp) => format!("expires {}", exp.format("%Y-%m-%d %H:%M:%S UTC")),
+        None => "expiry unknown".to_string(),
+    }
+}
+
+/// Send a hand-crafted payload to the ChatGPT Responses endpoint and copy the
+/// raw (unparsed) response body to `out` as it arrives.
+pub fn raw_request(
+    config: &Config,
+    payload: &serde_json::Value,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let tokens = get_valid_tokens(config)?
+        .ok_or_else(|| anyhow::anyhow!("Not authenticated. Run `jose login` first."))?;
+
+    let mut resp = chatgpt_request(config, &tokens, payload)?
+        .send()
+        .context("Failed to send request to ChatGPT")?;
+
+    let status = resp.status();
+    resp.copy_to(out).context("Failed to read response")?;
+    out.flush()?;
+
+    if !status.is_success() {
+        anyhow::bail!("API error: {}", status);
+    }
+    Ok(())
 }
 
 /// OpenAI-compatible backend: `{base_url}/chat/completions`, non-streaming.
@@ -86,6 +704,7 @@ fn call_openai_compatible(
     prompt: &str,
     model: &str,
     system_prompt: &str,
+    opts: &RequestOptions,
 ) -> Result<String> {
     let base_url = config.base_url().ok_or_else(|| {
         anyhow::anyhow!(
@@ -95,34 +714,28 @@ fn call_openai_compatible(
     })?;
     let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
 
-    let payload = serde_json::json!({
+    let mut payload = serde_json::json!({
         "model": model,
         "messages": [
             {"role": "system", "content": system_prompt},
-            {"role": "user", "content": prompt},
+            {"role": "user", "content": openai_content(prompt, opts.image)},
         ],
         "stream": false,
     });
+    opts.apply(&mut payload);
 
-    let mut req = reqwest::blocking::Client::new()
+    log::debug(&format!("POST {} (model {})", url, model));
+    let mut req = http::client()?
         .post(&url)
         .header("Content-Type", "application/json")
         .json(&payload)
-        .timeout(Duration::from_secs(120));
+        .timeout(config.request_timeout());
 
     if let Some(key) = config.api_key() {
         req = req.header("Authorization", format!("Bearer {}", key));
     }
 
-    let resp = req
-        .send()
-        .with_context(|| format!("Failed to send request to {}", url))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().unwrap_or_default();
-        anyhow::bail!("API error: {} - {}", status, body);
-    }
+    let resp = send(req, &url, config.max_retries)?;
 
     let data: serde_json::Value = resp.json().context("Invalid JSON response")?;
     let content = data["choices"][0]["message"]["content"]
@@ -131,3 +744,176 @@ fn call_openai_compatible(
 
     Ok(content.trim().to_string())
 }
+
+/// User message content for chat completions: plain text, or text plus an
+/// `image_url` part when an image is attached.
+fn openai_content(prompt: &str, image: Option<&ImageAttachment>) -> serde_json::Value {
+    match image {
+        None => serde_json::json!(prompt),
+        Some(image) => serde_json::json!([
+            {"type": "text", "text": prompt},
+            {"type": "image_url", "image_url": {"url": image.data_url}},
+        ]),
+    }
+}
+
+/// Look up `model` in an openai-compatible server's `{base_url}/models` listing.
+///
+/// Returns the raw entry (servers differ in which metadata they include), or
+/// `None` for the ChatGPT backend, which has no public models endpoint.
+pub fn fetch_model_entry(config: &Config, model: &str) -> Result<Option<serde_json::Value>> {
+    if config.provider != ProviderKind::OpenAiCompatible {
+        return Ok(None);
+    }
+    let Some(base_url) = config.base_url() else {
+        return Ok(None);
+    };
+    let url = format!("{}/models", base_url.trim_end_matches('/'));
+
+    let mut req = http::client()?.get(&url).timeout(config.request_timeout());
+    if let Some(key) = config.api_key() {
+        req = req.header("Authorization", format!("Bearer {}", key));
+    }
+
+    let resp = send(req, &url, config.max_retries)?;
+
+    let data: serde_json::Value = resp
+        .json()
+        .map_err(|e| timeout_hint(e, "request_timeout_secs"))
+        .context("Invalid JSON response")?;
+    Ok(data["data"]
+        .as_array()
+        .and_then(|models| models.iter().find(|m| m["id"] == model))
+        .cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Read};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answer one Chat Completions request with `content` and hand back the
+    /// JSON body it was sent.
+    fn mock_server(content: &str) -> (String, JoinHandle<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let reply = serde_json::json!({"choices": [{"message": {"content": content}}]}).to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                reply.len(),
+                reply
+            );
+            (&stream).write_all(response.as_bytes()).unwrap();
+            serde_json::from_slice(&body).unwrap()
+        });
+        (url, handle)
+    }
+
+    fn mock_config(base_url: String) -> Config {
+        Config {
+            provider: ProviderKind::OpenAiCompatible,
+            base_url: Some(base_url),
+            max_retries: 0,
+            ..Config::default()
+        }
+    }
+
+    /// Text deltas and final text of `stream`, read as a Responses API stream.
+    fn stream_text(stream: &str) -> (Vec<String>, String) {
+        let mut deltas = Vec::new();
+        let response = read_stream(stream.as_bytes(), &mut |d| deltas.push(d.to_string())).unwrap();
+        (deltas, response.text)
+    }
+
+    #[test]
+    fn unframed_data_lines_all_count() {
+        let (deltas, text) =
+            stream_text("data: {\"delta\":\"ls\"}\ndata: {\"delta\":\" -la\"}\n\n");
+        assert_eq!(deltas, ["ls", " -la"]);
+        assert_eq!(text, "ls -la");
+    }
+
+    #[test]
+    fn object_split_across_data_lines() {
+        let (deltas, _) = stream_text(
+            "data: {\"type\":\"response.output_text.delta\",\ndata: \"delta\":\"ls -la\"}\n\ndata: [DONE]\n\n",
+        );
+        assert_eq!(deltas, ["ls -la"]);
+    }
+
+    #[test]
+    fn final_response_is_typed() {
+        let stream = concat!(
+            "data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_1\",",
+            "\"model\":\"gpt-5-2025\",\"usage\":{\"input_tokens\":12,\"output_tokens\":3,",
+            "\"total_tokens\":15},\"output\":[{\"type\":\"reasoning\",\"content\":[]},",
+            "{\"type\":\"message\",\"role\":\"assistant\",\"content\":",
+            "[{\"type\":\"output_text\",\"text\":\"ls\"}]}]}}\n\n",
+        );
+        let response = read_stream(stream.as_bytes(), &mut |_| {}).unwrap();
+        assert_eq!(response.id, "resp_1");
+        assert_eq!(response.model, "gpt-5-2025");
+        let usage = response.usage.unwrap();
+        assert_eq!(
+            (usage.input_tokens, usage.output_tokens, usage.total_tokens),
+            (12, 3, 15)
+        );
+        // No deltas arrived, so the text comes from the output items.
+        assert_eq!(response.text, "ls");
+    }
+
+    #[test]
+    fn reasoning_deltas_are_skipped() {
+        let (_, text) = stream_text(concat!(
+            "data: {\"type\":\"response.reasoning_summary_text.delta\",\"delta\":\"hmm\"}\n\n",
+            "data: {\"type\":\"response.output_text.delta\",\"delta\":\"pwd\"}\n\n",
+        ));
+        assert_eq!(text, "pwd");
+    }
+
+    #[test]
+    fn seed_is_sent_with_temperature_zero() {
+        let (url, server) = mock_server("ls -la");
+        let opts = RequestOptions {
+            seed: Some(42),
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let text = generate(&mock_config(url), "list files", "m1", &opts).unwrap();
+        assert_eq!(text, "ls -la");
+
+        let payload = server.join().unwrap();
+        assert_eq!(payload["seed"], 42);
+        assert_eq!(payload["temperature"], 0.0);
+    }
+
+    #[test]
+    fn seed_is_left_out_by_default() {
+        let (url, server) = mock_server("ls -la");
+        generate(&mock_config(url), "list files", "m1", &Default::default()).unwrap();
+
+        let payload = server.join().unwrap();
+        assert!(payload.get("seed").is_none());
+        assert!(payload.get("temperature").is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 /// Represents the detected shell type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -8,29 +9,85 @@ pub enum ShellType {
     Bash,
     Zsh,
     Fish,
+    /// Windows PowerShell 5.1 (`powershell.exe`).
     PowerShell,
+    /// PowerShell 7+ (`pwsh`), cross-platform.
+    PowerShellCore,
     Cmd,
     Sh,
+    Nu,
+    Xonsh,
+    Elvish,
     Unknown,
 }
 
 impl ShellType {
+    /// Either PowerShell edition; they share quoting and escaping rules.
+    pub fn is_powershell(self) -> bool {
+        matches!(self, ShellType::PowerShell | ShellType::PowerShellCore)
+    }
+
     /// Returns a human-readable name for the shell
     pub fn name(&self) -> &'static str {
         match self {
             ShellType::Bash => "Bash",
             ShellType::Zsh => "Zsh",
             ShellType::Fish => "Fish",
-            ShellType::PowerShell => "PowerShell",
+            ShellType::PowerShell => "Windows PowerShell 5.1",
+            ShellType::PowerShellCore => "PowerShell 7 (pwsh)",
             ShellType::Cmd => "CMD",
             ShellType::Sh => "sh",
+            ShellType::Nu => "Nushell",
+            ShellType::Xonsh => "Xonsh",
+            ShellType::Elvish => "Elvish",
             ShellType::Unknown => "shell",
         }
     }
 }
 
+impl std::str::FromStr for ShellType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(ShellType::Bash),
+            "zsh" => Ok(ShellType::Zsh),
+            "fish" => Ok(ShellType::Fish),
+            "powershell" => Ok(ShellType::PowerShell),
+            "pwsh" => Ok(ShellType::PowerShellCore),
+            "cmd" => Ok(ShellType::Cmd),
+            "sh" => Ok(ShellType::Sh),
+            "nu" | "nushell" => Ok(ShellType::Nu),
+            "xonsh" => Ok(ShellType::Xonsh),
+            "elvish" => Ok(ShellType::Elvish),
+            _ => Err(format!(
+                "unknown shell `{}` (expected bash, zsh, fish, powershell, pwsh, cmd, sh, nu, xonsh or elvish)",
+                s
+            )),
+        }
+    }
+}
+
+/// Shell forced by `--shell` or the config, bypassing detection.
+static SHELL_OVERRIDE: OnceLock<ShellType> = OnceLock::new();
+
+/// Use `shell` instead of detecting it. Only the first call has an effect.
+pub fn set_shell_override(shell: ShellType) {
+    let _ = SHELL_OVERRIDE.set(shell);
+}
+
 /// Detects the current shell type based on environment variables
 pub fn detect_shell() -> ShellType {
+    if let Some(&shell) = SHELL_OVERRIDE.get() {
+        return shell;
+    }
+
+    // Variables the running shell exports itself beat `$SHELL`, which is only
+    // the login shell.
+    if let Some(shell) = detect_shell_markers() {
+        return shell;
+    }
+
     #[cfg(unix)]
     {
         detect_unix_shell()
@@ -47,33 +104,58 @@ pub fn detect_shell() -> ShellType {
     }
 }
 
+/// Shells that announce themselves through an environment variable.
+fn detect_shell_markers() -> Option<ShellType> {
+    let markers = [
+        ("NU_VERSION", ShellType::Nu),
+        ("XONSH_VERSION", ShellType::Xonsh),
+        ("ELVISH_VERSION", ShellType::Elvish),
+    ];
+    markers
+        .into_iter()
+        .find(|(var, _)| env::var_os(var).is_some())
+        .map(|(_, shell)| shell)
+}
+
+/// Shell type from a path or process name such as `/usr/bin/zsh` or `nu.exe`.
+fn shell_from_name(name: &str) -> Option<ShellType> {
+    let name = name.to_lowercase();
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(&name);
+    let base = base.trim_end_matches(".exe");
+    if name.contains("zsh") {
+        Some(ShellType::Zsh)
+    } else if name.contains("bash") {
+        Some(ShellType::Bash)
+    } else if name.contains("fish") {
+        Some(ShellType::Fish)
+    } else if name.contains("xonsh") {
+        Some(ShellType::Xonsh)
+    } else if name.contains("elvish") {
+        Some(ShellType::Elvish)
+    } else if name.contains("pwsh") {
+        Some(ShellType::PowerShellCore)
+    } else if name.contains("powershell") {
+        Some(ShellType::PowerShell)
+    } else if base == "nu" {
+        Some(ShellType::Nu)
+    } else if base == "sh" {
+        Some(ShellType::Sh)
+    } else {
+        None
+    }
+}
+
 #[cfg(unix)]
 fn detect_unix_shell() -> ShellType {
     // First check $SHELL environment variable
-    if let Ok(shell) = env::var("SHELL") {
-        let shell_lower = shell.to_lowercase();
-        if shell_lower.contains("zsh") {
-            return ShellType::Zsh;
-        } else if shell_lower.contains("bash") {
-            return ShellType::Bash;
-        } else if shell_lower.contains("fish") {
-            return ShellType::Fish;
-        } else if shell_lower.ends_with("/sh") {
-            return ShellType::Sh;
-        }
+    if let Some(shell) = env::var("SHELL").ok().as_deref().and_then(shell_from_name) {
+        return shell;
     }
 
     // Fallback: check parent process name via /proc on Linux
     #[cfg(target_os = "linux")]
-    if let Some(name) = parent_process_name() {
-        let name = name.to_lowercase();
-        if name.contains("zsh") {
-            return ShellType::Zsh;
-        } else if name.contains("bash") {
-            return ShellType::Bash;
-        } else if name.contains("fish") {
-            return ShellType::Fish;
-        }
+    if let Some(shell) = parent_process_name().as_deref().and_then(shell_from_name) {
+        return shell;
     }
 
     ShellType::Unknown
@@ -98,8 +180,8 @@ fn parent_process_name() -> Option<String> {
 fn detect_windows_shell() -> ShellType {
     // Check for PowerShell indicators
     // PSModulePath is set in PowerShell sessions
-    if env::var("PSModulePath").is_ok() {
-        return ShellType::PowerShell;
+    if let Ok(module_path) = env::var("PSModulePath") {
+        return powershell_edition(&module_path);
     }
 
     // Check COMSPEC for cmd.exe (default Windows shell)
@@ -123,6 +205,381 @@ fn detect_windows_shell() -> ShellType {
     ShellType::Cmd
 }
 
+/// Tell PowerShell 7+ from Windows PowerShell 5.1. `$PSEdition` is not an
+/// environment variable, so rely on `PSEdition` if the user exported it, then
+/// on the first `PSModulePath` entry: pwsh puts `Documents\PowerShell\Modules`
+/// (or `PowerShell\7\Modules`) first, 5.1 puts `WindowsPowerShell\Modules`.
+#[cfg(any(windows, test))]
+fn powershell_edition(module_path: &str) -> ShellType {
+    if let Ok(edition) = env::var("PSEdition") {
+        return if edition.eq_ignore_ascii_case("core") {
+            ShellType::PowerShellCore
+        } else {
+            ShellType::PowerShell
+        };
+    }
+    let first = module_path.split(';').next().unwrap_or_default();
+    if first.to_lowercase().contains("\\powershell\\") {
+        ShellType::PowerShellCore
+    } else {
+        ShellType::PowerShell
+    }
+}
+
+/// Build a process that runs `cmd` through `shell`, the way the user would.
+pub fn shell_command(shell: ShellType, cmd: &str) -> std::process::Command {
+    let (program, flags): (&str, &[&str]) = match shell {
+        ShellType::Bash => ("bash", &["-c"]),
+        ShellType::Zsh => ("zsh", &["-c"]),
+        ShellType::Fish => ("fish", &["-c"]),
+        ShellType::PowerShell => ("powershell", &["-NoProfile", "-Command"]),
+        ShellType::PowerShellCore => ("pwsh", &["-NoProfile", "-Command"]),
+        ShellType::Cmd => ("cmd", &["/C"]),
+        ShellType::Nu => ("nu", &["-c"]),
+        ShellType::Xonsh => ("xonsh", &["-c"]),
+        ShellType::Elvish => ("elvish", &["-c"]),
+        ShellType::Sh | ShellType::Unknown => ("sh", &["-c"]),
+    };
+    let mut command = std::process::Command::new(program);
+    command.args(flags).arg(cmd);
+    command
+}
+
+/// Strip a pasted shell-prompt prefix (`$ `, `PS> `, ...) from a generated command.
+///
+/// `# ` is left alone: it marks the model's "cannot be done" explanation line.
+/// A bare `> ` is only treated as a prompt on Windows shells, since on POSIX
+/// shells a leading `>` is a legitimate redirection (`> file` truncates it).
+pub fn strip_prompt_prefix(cmd: &str, shell: ShellType) -> &str {
+    let trimmed = cmd.trim_start();
+
+    if let Some(rest) = trimmed.strip_prefix("$ ") {
+        return rest.trim_start();
+    }
+
+    match shell {
+        ShellType::Zsh => trimmed.strip_prefix("% ").unwrap_or(trimmed).trim_start(),
+        ShellType::PowerShell | ShellType::PowerShellCore | ShellType::Cmd => {
+            // `PS> cmd`, `PS C:\Users\me> cmd`, `C:\> cmd`, `> cmd`
+            let is_ps_prompt = trimmed.starts_with("PS>") || trimmed.starts_with("PS ");
+            let prompt_end = if is_ps_prompt {
+                trimmed.find("> ").map(|i| i + 2)
+            } else if trimmed.get(1..3) == Some(":\\") {
+                // A drive prompt's `>` follows the path directly; a spaced
+                // one is a redirection (`C:\tools\x.exe > out.txt`).
+                trimmed
+                    .find('>')
+                    .filter(|&i| !trimmed[..i].ends_with(char::is_whitespace))
+                    .map(|i| i + 1)
+            } else if trimmed.starts_with("> ") {
+                Some(2)
+            } else {
+                None
+            };
+            match prompt_end {
+                Some(i) => trimmed[i..].trim_start(),
+                None => trimmed,
+            }
+        }
+        _ => trimmed,
+    }
+}
+
+/// `cmd` with quoted strings, escaped characters and comments blanked out
+/// (each character replaced by a space, newlines kept), following `shell`'s
+/// quoting rules, so callers only see bare words and operators. The output
+/// has exactly one character per input character. The flag is set if a
+/// quote is left open.
+fn bare_text(cmd: &str, shell: ShellType) -> (String, bool) {
+    let escape = match shell {
+        ShellType::PowerShell | ShellType::PowerShellCore => '`',
+        ShellType::Cmd => '^',
+        _ => '\\',
+    };
+    let posix = !(shell.is_powershell() || shell == ShellType::Cmd);
+    let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+
+    let chars: Vec<char> = cmd.chars().collect();
+    let mut out = String::with_capacity(cmd.len());
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        // Escapes only apply outside quotes and inside double quotes (and
+        // never inside quotes in cmd).
+        let escapes = match quote {
+            None => true,
+            Some(q) => q == '"' && shell != ShellType::Cmd,
+        };
+        if c == escape && escapes {
+            out.push(' ');
+            if let Some(&next) = chars.get(i + 1) {
+                out.push(blank(next));
+            }
+            i += 2;
+            continue;
+        }
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                out.push(blank(c));
+            }
+            None if c == '"' || (c == '\'' && shell != ShellType::Cmd) => {
+                quote = Some(c);
+                out.push(' ');
+            }
+            None if c == '#' && posix && (i == 0 || chars[i - 1].is_whitespace()) => {
+                // Comment: blank out the rest of the line.
+                while i < chars.len() && chars[i] != '\n' {
+                    out.push(' ');
+                    i += 1;
+                }
+                continue;
+            }
+            None => out.push(c),
+        }
+        i += 1;
+    }
+
+    (out, quote.is_some())
+}
+
+/// Cheap syntax sanity check for a generated command, tuned to `shell`'s
+/// quoting rules. Returns a description of each problem found.
+///
+/// This is not a parser: it only catches the shapes a truncated stream leaves
+/// behind (an unclosed quote or bracket, a dangling pipe or `&&`).
+pub fn lint_command(cmd: &str, shell: ShellType) -> Vec<&'static str> {
+    let mut issues = Vec::new();
+    let posix = !(shell.is_powershell() || shell == ShellType::Cmd);
+    let (bare, open_quote) = bare_text(cmd, shell);
+
+    let mut brackets: Vec<char> = Vec::new();
+    let mut unbalanced = false;
+    for c in bare.chars() {
+        if matches!(c, '(' | '[' | '{') {
+            brackets.push(c);
+        } else if matches!(c, ')' | ']' | '}') {
+            let open = match c {
+                ')' => '(',
+                ']' => '[',
+                _ => '{',
+            };
+            // A stray closer is tolerated: `case` patterns use a bare `)`.
+            if brackets.last().is_some_and(|&b| b != open) {
+                unbalanced = true;
+            }
+            brackets.pop();
+        }
+    }
+
+    if open_quote {
+        issues.push("unterminated quote");
+    }
+    if unbalanced || !brackets.is_empty() {
+        issues.push("unbalanced parentheses or brackets");
+    }
+    let tail = cmd.trim_end();
+    if tail.ends_with('|') || tail.ends_with("&&") || (posix && tail.ends_with('\\')) {
+        issues.push("ends with a dangling pipe, `&&` or line continuation");
+    }
+
+    issues
+}
+
+/// Split model output into commands: one per line, except that a command
+/// spanning several lines (a heredoc, a `for`/`if` block, an open quote or
+/// bracket, a trailing `\\`, `|` or `&&`) is kept together as one block.
+pub fn split_commands(text: &str, shell: ShellType) -> Vec<String> {
+    let posix = !(shell.is_powershell() || shell == ShellType::Cmd);
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    // The block minus heredoc bodies, which the syntax checks must not see.
+    let mut code: Vec<&str> = Vec::new();
+    // Delimiter of the heredoc we are inside, and whether it is `<<-`.
+    let mut heredoc: Option<(String, bool)> = None;
+
+    for line in text.lines() {
+        if current.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        current.push(line);
+
+        if let Some((delim, strip_tabs)) = &heredoc {
+            let body = if *strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if body.trim_end() != delim {
+                continue;
+            }
+            heredoc = None;
+        } else {
+            code.push(line);
+            if posix {
+                if let Some(start) = heredoc_start(line, shell) {
+                    heredoc = Some(start);
+                    continue;
+                }
+            }
+        }
+
+        if !continues(&code.join("\n"), shell) {
+            blocks.push(current.join("\n"));
+            current.clear();
+            code.clear();
+        }
+    }
+
+    // Whatever is left is unterminated; keep it so the lint can flag it.
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
+
+/// Delimiter and `<<-` flag of a heredoc opened on `line`, if any.
+///
+/// Only a bare `<<` counts: not one inside quotes or a comment, and not an
+/// arithmetic shift inside `((…))` / `$((…))`.
+fn heredoc_start(line: &str, shell: ShellType) -> Option<(String, bool)> {
+    let chars: Vec<char> = line.chars().collect();
+    let bare: Vec<char> = bare_text(line, shell).0.chars().collect();
+    let mut arithmetic = 0;
+    let mut i = 0;
+    while i + 1 < bare.len() {
+        match (bare[i], bare[i + 1]) {
+            ('(', '(') => arithmetic += 1,
+            (')', ')') if arithmetic > 0 => arithmetic -= 1,
+            ('<', '<') if arithmetic == 0 => {}
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        let is_redirect = bare[i] == '<';
+        i += 2;
+        if !is_redirect {
+            continue;
+        }
+        // `<<<` is a here-string, not a heredoc.
+        if bare.get(i) == Some(&'<') {
+            i += 1;
+            continue;
+        }
+        let strip_tabs = bare.get(i) == Some(&'-');
+        if strip_tabs {
+            i += 1;
+        }
+        while chars.get(i).is_some_and(|c| *c == ' ' || *c == '\t') {
+            i += 1;
+        }
+        if let Some(word) = heredoc_delimiter(&chars[i..]) {
+            return Some((word, strip_tabs));
+        }
+    }
+    None
+}
+
+/// The heredoc delimiter word at the start of `rest`: quoted (`'EOF'`,
+/// `"EOF"`, `\EOF`), or a bare word starting with a letter or underscore.
+fn heredoc_delimiter(rest: &[char]) -> Option<String> {
+    let word = |chars: &[char]| -> String {
+        chars
+            .iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .collect()
+    };
+    let delimiter = match *rest.first()? {
+        q @ ('\'' | '"') => {
+            let end = rest[1..].iter().position(|&c| c == q)?;
+            rest[1..=end].iter().collect()
+        }
+        '\\' => word(&rest[1..]),
+        c if c.is_alphabetic() || c == '_' => word(rest),
+        _ => return None,
+    };
+    Some(delimiter).filter(|d: &String| !d.is_empty())
+}
+
+/// Whether `block` is syntactically incomplete and swallows the next line.
+fn continues(block: &str, shell: ShellType) -> bool {
+    let tail = block.trim_end();
+    let continuation = match shell {
+        ShellType::PowerShell | ShellType::PowerShellCore => '`',
+        ShellType::Cmd | ShellType::Elvish => '^',
+        _ => '\\',
+    };
+    if tail.ends_with(continuation) || tail.ends_with('|') || tail.ends_with("&&") {
+        return true;
+    }
+    if !lint_command(block, shell).is_empty() {
+        return true;
+    }
+    keyword_depth(block, shell) > 0
+}
+
+/// Net count of open compound commands (`if`..`fi`, `for`..`done`, fish's
+/// `..end`). Only bare words count, not ones inside quotes or comments.
+fn keyword_depth(block: &str, shell: ShellType) -> i32 {
+    let (bare, _) = bare_text(block, shell);
+    let words = bare
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')'))
+        .filter(|w| !w.is_empty());
+    let mut depth = 0;
+    for word in words {
+        depth += match (shell, word) {
+            (ShellType::Fish, "if" | "for" | "while" | "function" | "begin" | "switch") => 1,
+            (ShellType::Fish, "end") => -1,
+            // Brace- or indentation-delimited blocks are caught by the bracket lint.
+            (ShellType::Fish | ShellType::PowerShell | ShellType::PowerShellCore, _) => 0,
+            (ShellType::Cmd, _) => 0,
+            (ShellType::Nu | ShellType::Xonsh | ShellType::Elvish, _) => 0,
+            (_, "if" | "case" | "for" | "while" | "until" | "select") => 1,
+            (_, "fi" | "esac" | "done") => -1,
+            _ => 0,
+        };
+    }
+    depth
+}
+
+/// The first of `protected` that `cmd` appears to reference, if any.
+///
+/// Heuristic: words that look like paths (`/…`, `~…`, `$HOME…`, or relative
+/// paths containing `/`) are expanded and compared component-wise, so
+/// `/etc` matches `/etc/hosts` but not `/etcetera`.
+pub fn protected_path<'a>(cmd: &str, protected: &'a [String]) -> Option<&'a str> {
+    let home = dirs::home_dir();
+    let cwd = env::current_dir().ok();
+    let expand = |word: &str| -> Option<PathBuf> {
+        let rest = word
+            .strip_prefix('~')
+            .or_else(|| word.strip_prefix("${HOME}"))
+            .or_else(|| word.strip_prefix("$HOME"));
+        match rest {
+            Some(rest) => Some(home.as_ref()?.join(rest.trim_start_matches(['/', '\\']))),
+            None if Path::new(word).is_absolute() => Some(PathBuf::from(word)),
+            None if word.contains('/') => Some(cwd.as_ref()?.join(word)),
+            None => None,
+        }
+    };
+
+    let words: Vec<PathBuf> = cmd
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '<' | '>' | '=' | '('))
+        .map(|w| w.trim_matches(['\'', '"', ')', '`']))
+        .filter(|w| !w.is_empty())
+        .filter_map(expand)
+        .collect();
+    protected.iter().map(String::as_str).find(|p| {
+        let Some(p) = expand(p) else { return false };
+        words.iter().any(|w| w.starts_with(&p))
+    })
+}
+
 /// Returns the OS name for display
 pub fn os_name() -> &'static str {
     if cfg!(target_os = "macos") {
@@ -239,3 +696,241 @@ fn detect_package_managers() -> Vec<&'static str> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Serializes tests that change the process environment.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    const MARKERS: [(&str, ShellType); 3] = [
+        ("NU_VERSION", ShellType::Nu),
+        ("XONSH_VERSION", ShellType::Xonsh),
+        ("ELVISH_VERSION", ShellType::Elvish),
+    ];
+
+    #[test]
+    fn detects_shells_from_version_variables() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved: Vec<_> = MARKERS.iter().map(|(var, _)| env::var_os(var)).collect();
+        for (var, _) in MARKERS {
+            env::remove_var(var);
+        }
+
+        assert_eq!(detect_shell_markers(), None);
+        for (var, shell) in MARKERS {
+            env::set_var(var, "0.1");
+            assert_eq!(detect_shell_markers(), Some(shell));
+            env::remove_var(var);
+        }
+
+        for ((var, _), value) in MARKERS.iter().zip(saved) {
+            if let Some(value) = value {
+                env::set_var(var, value);
+            }
+        }
+    }
+
+    #[test]
+    fn detects_shells_from_names() {
+        assert_eq!(shell_from_name("/usr/bin/nu"), Some(ShellType::Nu));
+        assert_eq!(shell_from_name("C:\\Tools\\nu.exe"), Some(ShellType::Nu));
+        assert_eq!(shell_from_name("/bin/xonsh"), Some(ShellType::Xonsh));
+        assert_eq!(shell_from_name("elvish"), Some(ShellType::Elvish));
+        assert_eq!(shell_from_name("/usr/bin/zsh"), Some(ShellType::Zsh));
+        // `nu` must be the whole name, not a substring.
+        assert_eq!(shell_from_name("/usr/bin/menu"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detects_shell_from_login_shell() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved = env::var_os("SHELL");
+
+        env::set_var("SHELL", "/opt/homebrew/bin/nu");
+        assert_eq!(detect_unix_shell(), ShellType::Nu);
+        env::set_var("SHELL", "/usr/bin/elvish");
+        assert_eq!(detect_unix_shell(), ShellType::Elvish);
+
+        match saved {
+            Some(value) => env::set_var("SHELL", value),
+            None => env::remove_var("SHELL"),
+        }
+    }
+
+    #[test]
+    fn tells_pwsh_from_windows_powershell() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved = env::var_os("PSEdition");
+        env::remove_var("PSEdition");
+
+        let core =
+            "C:\\Users\\me\\Documents\\PowerShell\\Modules;C:\\Program Files\\PowerShell\\Modules";
+        let desktop = "C:\\Users\\me\\Documents\\WindowsPowerShell\\Modules;C:\\Windows\\system32\\WindowsPowerShell\\v1.0\\Modules";
+        assert_eq!(powershell_edition(core), ShellType::PowerShellCore);
+        assert_eq!(powershell_edition(desktop), ShellType::PowerShell);
+
+        // An exported `PSEdition` wins over the module path.
+        env::set_var("PSEdition", "Core");
+        assert_eq!(powershell_edition(desktop), ShellType::PowerShellCore);
+        env::set_var("PSEdition", "Desktop");
+        assert_eq!(powershell_edition(core), ShellType::PowerShell);
+
+        match saved {
+            Some(value) => env::set_var("PSEdition", value),
+            None => env::remove_var("PSEdition"),
+        }
+    }
+
+    #[test]
+    fn pwsh_and_powershell_are_distinct() {
+        assert_eq!(shell_from_name("pwsh.exe"), Some(ShellType::PowerShellCore));
+        assert_eq!(
+            shell_from_name("/usr/local/bin/pwsh"),
+            Some(ShellType::PowerShellCore)
+        );
+        assert_eq!(
+            shell_from_name("powershell.exe"),
+            Some(ShellType::PowerShell)
+        );
+        assert_eq!(
+            "pwsh".parse::<ShellType>().unwrap(),
+            ShellType::PowerShellCore
+        );
+        assert_eq!(
+            "powershell".parse::<ShellType>().unwrap(),
+            ShellType::PowerShell
+        );
+        assert_ne!(
+            ShellType::PowerShell.name(),
+            ShellType::PowerShellCore.name()
+        );
+
+        let program = |shell| shell_command(shell, "Get-Date").get_program().to_owned();
+        assert_eq!(program(ShellType::PowerShellCore), "pwsh");
+        assert_eq!(program(ShellType::PowerShell), "powershell");
+    }
+
+    #[test]
+    fn names_new_shells() {
+        for (name, shell, display) in [
+            ("nu", ShellType::Nu, "Nushell"),
+            ("xonsh", ShellType::Xonsh, "Xonsh"),
+            ("elvish", ShellType::Elvish, "Elvish"),
+        ] {
+            assert_eq!(name.parse::<ShellType>().unwrap(), shell);
+            assert_eq!(shell.name(), display);
+        }
+    }
+
+    #[test]
+    fn strips_posix_prompts() {
+        assert_eq!(strip_prompt_prefix("$ ls -la", ShellType::Bash), "ls -la");
+        assert_eq!(strip_prompt_prefix("% ls -la", ShellType::Zsh), "ls -la");
+        assert_eq!(strip_prompt_prefix("% ls -la", ShellType::Bash), "% ls -la");
+        assert_eq!(
+            strip_prompt_prefix("> out.txt", ShellType::Bash),
+            "> out.txt"
+        );
+        assert_eq!(
+            strip_prompt_prefix("# not possible", ShellType::Bash),
+            "# not possible"
+        );
+    }
+
+    #[test]
+    fn strips_powershell_prompts() {
+        let ps = ShellType::PowerShell;
+        assert_eq!(strip_prompt_prefix("PS> Get-Date", ps), "Get-Date");
+        assert_eq!(
+            strip_prompt_prefix("PS C:\\Users\\me> Get-Date", ps),
+            "Get-Date"
+        );
+        assert_eq!(
+            strip_prompt_prefix("> Get-Date", ShellType::PowerShellCore),
+            "Get-Date"
+        );
+    }
+
+    #[test]
+    fn strips_cmd_prompts() {
+        let cmd = ShellType::Cmd;
+        assert_eq!(strip_prompt_prefix("C:\\> dir", cmd), "dir");
+        assert_eq!(strip_prompt_prefix("C:\\Users\\me>dir /b", cmd), "dir /b");
+        assert_eq!(
+            strip_prompt_prefix("C:\\Windows\\System32\\ipconfig.exe > ip.txt", cmd),
+            "C:\\Windows\\System32\\ipconfig.exe > ip.txt"
+        );
+        assert_eq!(
+            strip_prompt_prefix("C:\\tools\\x.exe /all", cmd),
+            "C:\\tools\\x.exe /all"
+        );
+    }
+
+    #[test]
+    fn for_loop_is_one_command() {
+        let text = "for f in *.log; do\n  gzip \"$f\"\ndone\nls *.gz";
+        assert_eq!(
+            split_commands(text, ShellType::Bash),
+            ["for f in *.log; do\n  gzip \"$f\"\ndone", "ls *.gz"]
+        );
+    }
+
+    #[test]
+    fn heredoc_is_one_command() {
+        let text = "cat <<EOF > notes.txt\nif this\nthen (that\nEOF\necho done";
+        assert_eq!(
+            split_commands(text, ShellType::Bash),
+            [
+                "cat <<EOF > notes.txt\nif this\nthen (that\nEOF",
+                "echo done"
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_heredoc_delimiter() {
+        let text = "cat <<-'END' > run.sh\n\techo $HOME\n\tEND\nchmod +x run.sh";
+        assert_eq!(split_commands(text, ShellType::Bash).len(), 2);
+    }
+
+    #[test]
+    fn arithmetic_shift_is_not_a_heredoc() {
+        let text = "echo $((1 << 4))\nls -la\ndu -sh .";
+        assert_eq!(
+            split_commands(text, ShellType::Bash),
+            ["echo $((1 << 4))", "ls -la", "du -sh ."]
+        );
+        let text = "((x = y << z))\necho \"a << b\"\nls";
+        assert_eq!(split_commands(text, ShellType::Bash).len(), 3);
+        // A delimiter can't start with a digit.
+        assert_eq!(split_commands("echo 1 <<2\nls", ShellType::Bash).len(), 2);
+    }
+
+    #[test]
+    fn keywords_in_quotes_do_not_open_blocks() {
+        let text = "git commit -m \"fix for login\"\ngit commit --amend";
+        assert_eq!(
+            split_commands(text, ShellType::Bash),
+            ["git commit -m \"fix for login\"", "git commit --amend"]
+        );
+
+        let text = "echo 'stop if full' >> notes.txt\nprintf 'x'";
+        assert_eq!(split_commands(text, ShellType::Zsh).len(), 2);
+    }
+
+    #[test]
+    fn keywords_in_comments_do_not_open_blocks() {
+        let text = "ls -la # for details\ndu -sh .";
+        assert_eq!(split_commands(text, ShellType::Bash).len(), 2);
+    }
+
+    #[test]
+    fn fish_block_ends_with_end() {
+        let text = "for f in *.txt\n  echo $f\nend\npwd";
+        assert_eq!(split_commands(text, ShellType::Fish).len(), 2);
+    }
+}
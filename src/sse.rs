@@ -0,0 +1,155 @@
+//! Minimal Server-Sent Events reader
+
+use std::io::{self, BufRead};
+
+/// One dispatched event: its `event:` name and its `data:` lines, joined
+/// with `\n` as the SSE spec prescribes.
+#[derive(Debug, Default)]
+pub struct Event {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+impl Event {
+    /// The data lines one by one, for servers that put a whole JSON payload
+    /// on each `data:` line without blank lines between them.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.data.split('\n')
+    }
+}
+
+/// Iterator over the events of an SSE stream. Fields are buffered until a
+/// blank line ends the event, so payloads split across reads or spread over
+/// several `data:` lines arrive whole.
+pub struct Events<R> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> Events<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = Event::default();
+        let mut has_data = false;
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    // A stream may end without the final blank line.
+                    return has_data.then_some(Ok(event));
+                }
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if has_data {
+                    return Some(Ok(event));
+                }
+                event.event = None;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue; // comment / keep-alive
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "data" => {
+                    if has_data {
+                        event.data.push('\n');
+                    }
+                    event.data.push_str(value);
+                    has_data = true;
+                }
+                "event" => event.event = Some(value.to_string()),
+                _ => {} // `id:` and `retry:` are of no use to a one-shot request
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    /// A reader that hands out at most `size` bytes per read, so lines and
+    /// events straddle buffer refills.
+    struct Chunked<'a> {
+        data: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.size.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn events(input: &str, chunk: usize) -> Vec<Event> {
+        let reader = Chunked {
+            data: input.as_bytes(),
+            size: chunk,
+        };
+        Events::new(BufReader::with_capacity(4, reader))
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn line_split_across_reads() {
+        let input = "event: response.output_text.delta\ndata: {\"delta\":\"ls -la\"}\n\n";
+        for chunk in [1, 3, 7] {
+            let events = events(input, chunk);
+            assert_eq!(events.len(), 1);
+            assert_eq!(
+                events[0].event.as_deref(),
+                Some("response.output_text.delta")
+            );
+            assert_eq!(events[0].data, "{\"delta\":\"ls -la\"}");
+        }
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let events = events("data: one\r\n\r\n: keep-alive\r\ndata: two\r\n\r\n", 5);
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, ["one", "two"]);
+    }
+
+    #[test]
+    fn multi_line_data_is_joined() {
+        let events = events("data: {\"a\":\ndata: 1}\n\n", 64);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"a\":\n1}");
+        assert_eq!(events[0].lines().collect::<Vec<_>>(), ["{\"a\":", "1}"]);
+    }
+
+    #[test]
+    fn trailing_event_without_blank_line() {
+        let events = events("data: first\n\ndata: [DONE]", 2);
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, ["first", "[DONE]"]);
+    }
+
+    #[test]
+    fn event_name_without_data_is_dropped() {
+        let events = events("event: ping\n\ndata: x\n\n", 64);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, None);
+    }
+}